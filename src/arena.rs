@@ -0,0 +1,299 @@
+use core::alloc::{AllocError, Allocator, GlobalAlloc, Layout};
+use core::fmt;
+use core::ptr::NonNull;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::spin::SpinLock;
+use crate::unix::{errno, exclude_from_dumps, page_size};
+use crate::{Backend, EmergencyWipe, OwnsAddress, WideVolatile, WipeStrategy};
+
+const GRANULE: usize = 16;
+
+/// A fixed-size heap for key material, in the spirit of libsodium's secure memory.
+///
+/// On first use (or [`init`](Self::init)) it reserves its region with `mmap`, `mlock`s it so it
+/// never hits swap and marks it `MADV_DONTDUMP` so it stays out of core dumps. Blocks are
+/// served from that region only, in 16-byte granules, and are zeroed when freed; when the region
+/// is exhausted, or could not be mapped and locked, allocations fail instead of falling back to
+/// ordinary memory.
+///
+/// It implements both [`GlobalAlloc`] and [`Allocator`], so it can back a
+/// [`ZeroizingGlobalAllocator`](crate::ZeroizingGlobalAllocator), a
+/// [`ZeroizingAllocator`](crate::ZeroizingAllocator) or a
+/// [`SensitiveAllocator`](crate::SensitiveAllocator).
+pub struct SecureArena {
+    size: usize,
+    region: SpinLock<Option<Result<Region, ArenaError>>>,
+    // Bounds of the block area, readable without the lock for `owns`.
+    start: AtomicUsize,
+    end: AtomicUsize,
+}
+
+/// Why a [`SecureArena`] could not set up its region, with the `errno` of the failing call.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArenaError {
+    /// `mmap` failed.
+    Map(i32),
+    /// `mlock` failed, usually because of `RLIMIT_MEMLOCK`.
+    Lock(i32),
+}
+
+impl fmt::Display for ArenaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArenaError::Map(errno) => write!(f, "mmap failed with errno {errno}"),
+            ArenaError::Lock(errno) => write!(f, "mlock failed with errno {errno}"),
+        }
+    }
+}
+
+/// The mapping: a bitmap of used granules followed by the granules themselves.
+struct Region {
+    map: *mut u8,
+    map_len: usize,
+    bitmap: *mut u64,
+    data: *mut u8,
+    granules: usize,
+}
+
+unsafe impl Send for Region {}
+
+impl Region {
+    unsafe fn map(size: usize) -> Result<Self, ArenaError> {
+        let page = page_size();
+        let data_len = size.div_ceil(page).max(1) * page;
+        let granules = data_len / GRANULE;
+        let bitmap_len = (granules / 8).div_ceil(page) * page;
+        let map_len = bitmap_len + data_len;
+        let map = libc::mmap(
+            core::ptr::null_mut(),
+            map_len,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+            -1,
+            0,
+        );
+        if map == libc::MAP_FAILED {
+            return Err(ArenaError::Map(errno()));
+        }
+        if libc::mlock(map, map_len) != 0 {
+            let err = errno();
+            libc::munmap(map, map_len);
+            return Err(ArenaError::Lock(err));
+        }
+        exclude_from_dumps(map, map_len);
+        let map = map as *mut u8;
+        Ok(Self {
+            map,
+            map_len,
+            bitmap: map as *mut u64,
+            data: map.add(bitmap_len),
+            granules,
+        })
+    }
+
+    fn is_used(&self, granule: usize) -> bool {
+        unsafe { *self.bitmap.add(granule / 64) & (1 << (granule % 64)) != 0 }
+    }
+
+    fn set_used(&mut self, granules: core::ops::Range<usize>, used: bool) {
+        for granule in granules {
+            let word = unsafe { &mut *self.bitmap.add(granule / 64) };
+            if used {
+                *word |= 1 << (granule % 64);
+            } else {
+                *word &= !(1 << (granule % 64));
+            }
+        }
+    }
+
+    fn all_free(&self, granules: core::ops::Range<usize>) -> bool {
+        granules.end <= self.granules && !granules.clone().any(|granule| self.is_used(granule))
+    }
+
+    /// First fit: returns the first granule of a free run of `count` granules aligned to
+    /// `align`, and marks it used.
+    fn claim(&mut self, count: usize, align: usize) -> Option<usize> {
+        let stride = (align / GRANULE).max(1);
+        let mut start = self.data.align_offset(align) / GRANULE;
+        while start + count <= self.granules {
+            match (start..start + count).find(|&granule| self.is_used(granule)) {
+                None => {
+                    self.set_used(start..start + count, true);
+                    return Some(start);
+                }
+                // Skip past the used granule, staying aligned.
+                Some(used) => start += (used - start) / stride * stride + stride,
+            }
+        }
+        None
+    }
+
+    fn index(&self, ptr: *mut u8) -> usize {
+        (ptr as usize - self.data as usize) / GRANULE
+    }
+}
+
+fn granules(size: usize) -> usize {
+    size.div_ceil(GRANULE)
+}
+
+impl SecureArena {
+    /// An arena serving up to `size` bytes (rounded up to whole pages). Nothing is mapped until
+    /// the first allocation or [`init`](Self::init).
+    pub const fn new(size: usize) -> Self {
+        Self {
+            size,
+            region: SpinLock::new(None),
+            start: AtomicUsize::new(0),
+            end: AtomicUsize::new(0),
+        }
+    }
+
+    /// Maps and locks the region now rather than on first use, reporting why that failed.
+    pub fn init(&self) -> Result<(), ArenaError> {
+        self.with_region(|_| ())
+    }
+
+    /// Runs `f` on the region, mapping it first if needed.
+    fn with_region<R>(&self, f: impl FnOnce(&mut Region) -> R) -> Result<R, ArenaError> {
+        self.region.with(|region| {
+            let region = region.get_or_insert_with(|| {
+                let mapped = unsafe { Region::map(self.size) };
+                if let Ok(mapped) = &mapped {
+                    self.start.store(mapped.data as usize, Ordering::Release);
+                    self.end.store(
+                        mapped.data as usize + mapped.granules * GRANULE,
+                        Ordering::Release,
+                    );
+                }
+                mapped
+            });
+            match region {
+                Ok(region) => Ok(f(region)),
+                Err(err) => Err(*err),
+            }
+        })
+    }
+}
+
+impl Drop for SecureArena {
+    fn drop(&mut self) {
+        self.region.with(|region| {
+            if let Some(Ok(region)) = region.take() {
+                unsafe {
+                    WideVolatile.wipe(region.map, region.map_len);
+                    libc::munlock(region.map as *const libc::c_void, region.map_len);
+                    libc::munmap(region.map as *mut libc::c_void, region.map_len);
+                }
+            }
+        });
+    }
+}
+
+unsafe impl GlobalAlloc for SecureArena {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let count = granules(layout.size()).max(1);
+        self.with_region(|region| {
+            region
+                .claim(count, layout.align())
+                .map_or(core::ptr::null_mut(), |start| region.data.add(start * GRANULE))
+        })
+        .unwrap_or(core::ptr::null_mut())
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        let count = granules(layout.size()).max(1);
+        // Free granules are always zero; wipe before anyone else can claim them.
+        WideVolatile.wipe(ptr, count * GRANULE);
+        let _ = self.with_region(|region| {
+            let start = region.index(ptr);
+            region.set_used(start..start + count, false);
+        });
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        self.alloc(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        if self.resize_in_place(ptr, layout, new_size) {
+            return ptr;
+        }
+        let new_layout = Layout::from_size_align_unchecked(new_size, layout.align());
+        let new_ptr = self.alloc(new_layout);
+        if !new_ptr.is_null() {
+            core::ptr::copy_nonoverlapping(ptr, new_ptr, layout.size().min(new_size));
+            self.dealloc(ptr, layout);
+        }
+        new_ptr
+    }
+}
+
+unsafe impl Allocator for SecureArena {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        if layout.size() == 0 {
+            let dangling = unsafe {
+                NonNull::new_unchecked(core::ptr::without_provenance_mut(layout.align()))
+            };
+            return Ok(NonNull::slice_from_raw_parts(dangling, 0));
+        }
+        let ptr = NonNull::new(unsafe { self.alloc(layout) }).ok_or(AllocError)?;
+        Ok(NonNull::slice_from_raw_parts(ptr, granules(layout.size()) * GRANULE))
+    }
+
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.allocate(layout)
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        if layout.size() != 0 {
+            self.dealloc(ptr.as_ptr(), layout);
+        }
+    }
+}
+
+unsafe impl Backend for SecureArena {
+    unsafe fn resize_in_place(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> bool {
+        let old = granules(layout.size()).max(1);
+        let new = granules(new_size).max(1);
+        if new < old {
+            WideVolatile.wipe(ptr.add(new * GRANULE), (old - new) * GRANULE);
+        }
+        self.with_region(|region| {
+            let start = region.index(ptr);
+            if new > old {
+                if !region.all_free(start + old..start + new) {
+                    return false;
+                }
+                region.set_used(start + old..start + new, true);
+            } else {
+                region.set_used(start + new..start + old, false);
+            }
+            true
+        })
+        .unwrap_or(false)
+    }
+
+    unsafe fn usable_size(&self, _ptr: *mut u8, layout: Layout) -> usize {
+        granules(layout.size()).max(1) * GRANULE
+    }
+}
+
+unsafe impl OwnsAddress for SecureArena {
+    fn owns(&self, ptr: *const u8) -> bool {
+        let start = self.start.load(Ordering::Acquire);
+        start != 0 && (start..self.end.load(Ordering::Acquire)).contains(&(ptr as usize))
+    }
+}
+
+impl EmergencyWipe for SecureArena {
+    /// Zeroes the whole block area, live blocks included, without taking the lock.
+    fn emergency_wipe(&self) {
+        let start = self.start.load(Ordering::Acquire);
+        if start != 0 {
+            let len = self.end.load(Ordering::Acquire) - start;
+            unsafe { WideVolatile.wipe(start as *mut u8, len) };
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use core::alloc::Layout;
+
+/// Optional capabilities of an inner allocator that the zeroizing wrappers use to avoid
+/// copying and wiping memory that never moves.
+///
+/// Wrappers only use them when built with
+/// [`with_backend`](crate::ZeroizingGlobalAllocator::with_backend); any allocator can be
+/// wrapped without implementing this trait. Every method has a conservative default.
+///
+/// # Safety
+///
+/// Implementations must uphold the contract documented on each method.
+pub unsafe trait Backend {
+    /// Resizes the block at `ptr`, allocated with `layout`, to `new_size` bytes without moving
+    /// it, returning whether that succeeded.
+    ///
+    /// After a successful call the block must be valid for `new_size` bytes and must be
+    /// deallocated (or resized again) with a layout of `new_size` bytes and `layout.align()`.
+    /// A failed call must leave the block untouched. When shrinking, the wrappers wipe the
+    /// bytes past `new_size` after the call, up to [`usable_size`](Backend::usable_size) for
+    /// the new size, so anything beyond that which the call gives back must be wiped by the
+    /// implementation. The default never resizes in place.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must denote a block currently allocated by `self` with `layout`, and `new_size`
+    /// must be non-zero and must not overflow `isize` when rounded up to `layout.align()`.
+    unsafe fn resize_in_place(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> bool {
+        let _ = (ptr, layout, new_size);
+        false
+    }
+
+    /// Returns how many bytes of the block at `ptr`, allocated with `layout`, can actually hold
+    /// data, which is at least `layout.size()`.
+    ///
+    /// Used by the wrappers' usable-size mode to wipe size-class slack as well. The default
+    /// reports `layout.size()`.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must denote a block currently allocated by `self` with `layout`, and
+    /// `layout.size()` must be non-zero.
+    unsafe fn usable_size(&self, ptr: *mut u8, layout: Layout) -> usize {
+        let _ = ptr;
+        layout.size()
+    }
+}
+
+unsafe impl<B: Backend + ?Sized> Backend for &B {
+    #[cfg_attr(feature = "aggressive-inline", inline(always))]
+    unsafe fn resize_in_place(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> bool {
+        (**self).resize_in_place(ptr, layout, new_size)
+    }
+
+    #[cfg_attr(feature = "aggressive-inline", inline(always))]
+    unsafe fn usable_size(&self, ptr: *mut u8, layout: Layout) -> usize {
+        (**self).usable_size(ptr, layout)
+    }
+}
+
+/// `Global` has no way to resize a block in place without possibly moving it, so wrappers
+/// around it always copy on `grow` and `shrink`.
+unsafe impl Backend for alloc::alloc::Global {}
+
+/// Where a zeroizing wrapper gets the [`Backend`] capabilities of its inner allocator `A` from:
+/// [`NoBackend`] or [`UseBackend`].
+///
+/// # Safety
+///
+/// Implementations must uphold the contract of the corresponding [`Backend`] methods for
+/// blocks allocated by `alloc`.
+pub unsafe trait BackendOf<A> {
+    /// See [`Backend::resize_in_place`].
+    ///
+    /// # Safety
+    ///
+    /// As for [`Backend::resize_in_place`], with `alloc` in place of `self`.
+    unsafe fn resize_in_place(alloc: &A, ptr: *mut u8, layout: Layout, new_size: usize) -> bool;
+
+    /// See [`Backend::usable_size`].
+    ///
+    /// # Safety
+    ///
+    /// As for [`Backend::usable_size`], with `alloc` in place of `self`.
+    unsafe fn usable_size(alloc: &A, ptr: *mut u8, layout: Layout) -> usize;
+}
+
+/// The wrappers' default: the inner allocator is treated as opaque, so blocks are never
+/// resized in place and only `layout.size()` bytes are known to be usable.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoBackend;
+
+unsafe impl<A> BackendOf<A> for NoBackend {
+    #[cfg_attr(feature = "aggressive-inline", inline(always))]
+    unsafe fn resize_in_place(alloc: &A, ptr: *mut u8, layout: Layout, new_size: usize) -> bool {
+        let _ = (alloc, ptr, layout, new_size);
+        false
+    }
+
+    #[cfg_attr(feature = "aggressive-inline", inline(always))]
+    unsafe fn usable_size(alloc: &A, ptr: *mut u8, layout: Layout) -> usize {
+        let _ = (alloc, ptr);
+        layout.size()
+    }
+}
+
+/// Selected by the wrappers' `with_backend` constructors: the inner allocator's own [`Backend`]
+/// implementation is used.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct UseBackend;
+
+unsafe impl<A: Backend> BackendOf<A> for UseBackend {
+    #[cfg_attr(feature = "aggressive-inline", inline(always))]
+    unsafe fn resize_in_place(alloc: &A, ptr: *mut u8, layout: Layout, new_size: usize) -> bool {
+        alloc.resize_in_place(ptr, layout, new_size)
+    }
+
+    #[cfg_attr(feature = "aggressive-inline", inline(always))]
+    unsafe fn usable_size(alloc: &A, ptr: *mut u8, layout: Layout) -> usize {
+        alloc.usable_size(ptr, layout)
+    }
+}
+
+/// Allocators that serve blocks from known address ranges and can tell whether they own a
+/// block by its address alone.
+///
+/// # Safety
+///
+/// `owns` must return `true` for every block currently allocated by `self`, and `false` for
+/// every block that is not.
+pub unsafe trait OwnsAddress {
+    /// Whether `ptr` lies in memory this allocator serves blocks from.
+    fn owns(&self, ptr: *const u8) -> bool;
+}
+
+unsafe impl<O: OwnsAddress + ?Sized> OwnsAddress for &O {
+    #[cfg_attr(feature = "aggressive-inline", inline(always))]
+    fn owns(&self, ptr: *const u8) -> bool {
+        (**self).owns(ptr)
+    }
+}
+
+/// `System` frees blocks without looking at their size, so any size up to what `malloc`
+/// actually reserved is a valid size for the block.
+///
+/// Shrinking only stays in place while the block keeps more than half of that, roughly its
+/// size class; smaller sizes move, so `malloc` gets the rest of a large block back.
+#[cfg(feature = "std")]
+unsafe impl Backend for std::alloc::System {
+    #[cfg_attr(feature = "aggressive-inline", inline(always))]
+    unsafe fn resize_in_place(&self, ptr: *mut u8, _layout: Layout, new_size: usize) -> bool {
+        malloc_usable_size(ptr).is_some_and(|usable| new_size <= usable && new_size > usable / 2)
+    }
+
+    #[cfg_attr(feature = "aggressive-inline", inline(always))]
+    unsafe fn usable_size(&self, ptr: *mut u8, layout: Layout) -> usize {
+        malloc_usable_size(ptr).map_or(layout.size(), |usable| usable.max(layout.size()))
+    }
+}
+
+#[cfg(all(feature = "std", any(target_os = "linux", target_os = "android", target_os = "freebsd")))]
+unsafe fn malloc_usable_size(ptr: *mut u8) -> Option<usize> {
+    Some(libc::malloc_usable_size(ptr as _))
+}
+
+#[cfg(all(feature = "std", any(target_os = "macos", target_os = "ios")))]
+unsafe fn malloc_usable_size(ptr: *mut u8) -> Option<usize> {
+    Some(libc::malloc_size(ptr as _))
+}
+
+#[cfg(all(
+    feature = "std",
+    not(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "freebsd",
+        target_os = "macos",
+        target_os = "ios"
+    ))
+))]
+unsafe fn malloc_usable_size(_ptr: *mut u8) -> Option<usize> {
+    None
+}
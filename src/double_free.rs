@@ -0,0 +1,112 @@
+use core::alloc::Layout;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::trace::event;
+
+const WAYS: usize = 4;
+
+/// Remembers recently freed blocks of the wrappers it is attached to, so freeing one of them
+/// again is caught before the wrapper wipes memory that may already belong to someone else.
+///
+/// Freed addresses are kept in a lock-free table of `N` slots (1024 by default), where each
+/// address hashes to a set of four. A block is forgotten when an allocation returns its address
+/// again, or when newer blocks freed into the same set push it out, so double frees are caught
+/// on a best-effort basis; two threads freeing the same block at the same moment may both get
+/// through. A larger `N` catches double frees further apart, at 8 bytes per slot. Zero-sized
+/// blocks are not tracked.
+pub struct DoubleFreeTracker<const N: usize = 1024> {
+    slots: [AtomicUsize; N],
+    handler: DoubleFreeHandler,
+}
+
+/// Called with the block and layout of a detected double free. If it returns, the wrapper
+/// neither wipes nor frees the block.
+pub type DoubleFreeHandler = fn(ptr: *mut u8, layout: Layout);
+
+/// What the wrappers need from a [`DoubleFreeTracker`] of any size.
+pub(crate) trait FreedBlocks: Sync {
+    /// Records a block handed out by an allocation; its address is no longer freed.
+    fn allocated(&self, ptr: *mut u8);
+
+    /// Records a block about to be freed, returning `false` (after calling the handler) if it
+    /// already was.
+    fn freeing(&self, ptr: *mut u8, layout: Layout) -> bool;
+}
+
+impl<const N: usize> DoubleFreeTracker<N> {
+    /// A tracker that prints a diagnostic and aborts the process on a double free.
+    #[cfg(feature = "std")]
+    pub const fn new() -> Self {
+        Self::with_handler(abort_on_double_free)
+    }
+
+    /// A tracker that calls `handler` on a double free.
+    ///
+    /// # Panics
+    ///
+    /// If `N` is not a non-zero multiple of 4; in a `static` this is a compile error.
+    pub const fn with_handler(handler: DoubleFreeHandler) -> Self {
+        assert!(N != 0 && N.is_multiple_of(WAYS), "DoubleFreeTracker needs a multiple of 4 slots");
+        Self {
+            slots: [const { AtomicUsize::new(0) }; N],
+            handler,
+        }
+    }
+
+    fn hash(ptr: *mut u8) -> usize {
+        ((ptr as usize) >> 4).wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 16
+    }
+
+    fn set(&self, ptr: *mut u8) -> &[AtomicUsize] {
+        let start = Self::hash(ptr) % (N / WAYS) * WAYS;
+        &self.slots[start..start + WAYS]
+    }
+}
+
+impl<const N: usize> FreedBlocks for DoubleFreeTracker<N> {
+    fn allocated(&self, ptr: *mut u8) {
+        if ptr.is_null() {
+            return;
+        }
+        for slot in self.set(ptr) {
+            let _ = slot.compare_exchange(ptr as usize, 0, Ordering::AcqRel, Ordering::Relaxed);
+        }
+    }
+
+    fn freeing(&self, ptr: *mut u8, layout: Layout) -> bool {
+        if layout.size() == 0 {
+            return true;
+        }
+        let set = self.set(ptr);
+        if set.iter().any(|slot| slot.load(Ordering::Acquire) == ptr as usize) {
+            event!(tracing::Level::ERROR, ptr = ?ptr, size = layout.size(), "double free");
+            (self.handler)(ptr, layout);
+            return false;
+        }
+        let stored = set.iter().any(|slot| {
+            slot.compare_exchange(0, ptr as usize, Ordering::AcqRel, Ordering::Relaxed).is_ok()
+        });
+        if !stored {
+            // The set is full: push out the entry other hash bits pick, so addresses sharing
+            // a set do not always evict the same way.
+            set[(Self::hash(ptr) / (N / WAYS)) % WAYS].store(ptr as usize, Ordering::Release);
+        }
+        true
+    }
+}
+
+#[cfg(feature = "std")]
+impl<const N: usize> Default for DoubleFreeTracker<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "std")]
+fn abort_on_double_free(ptr: *mut u8, layout: Layout) {
+    crate::fatal::abort(format_args!(
+        "double free of {ptr:p} (size {}, align {})",
+        layout.size(),
+        layout.align()
+    ))
+}
@@ -0,0 +1,130 @@
+use core::cell::UnsafeCell;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::Registry;
+
+const MAX_TARGETS: usize = 16;
+
+/// Memory that can be wiped in one go while the process is going down.
+///
+/// Implementations must be async-signal-safe: no locks, no allocation, nothing but atomics and
+/// volatile stores, since they may run from a signal handler or at exit while other threads
+/// still hold locks.
+pub trait EmergencyWipe: Sync {
+    /// Zeroes everything this target holds, without freeing it.
+    fn emergency_wipe(&self);
+}
+
+impl EmergencyWipe for Registry {
+    fn emergency_wipe(&self) {
+        self.emergency_wipe_all();
+    }
+}
+
+/// Append-only list of targets, readable without locking.
+struct Targets {
+    slots: [UnsafeCell<Option<&'static dyn EmergencyWipe>>; MAX_TARGETS],
+    /// Slots handed out to registering threads.
+    claimed: AtomicUsize,
+    /// Slots fully written; only these are read.
+    published: AtomicUsize,
+}
+
+unsafe impl Sync for Targets {}
+
+static TARGETS: Targets = Targets {
+    slots: [const { UnsafeCell::new(None) }; MAX_TARGETS],
+    claimed: AtomicUsize::new(0),
+    published: AtomicUsize::new(0),
+};
+
+/// Adds `target` to what [`emergency_wipe_all`] wipes. Returns `false` when 16 targets are
+/// already registered.
+pub fn register_emergency_wipe(target: &'static dyn EmergencyWipe) -> bool {
+    let index = TARGETS.claimed.fetch_add(1, Ordering::Relaxed);
+    if index >= MAX_TARGETS {
+        TARGETS.claimed.fetch_sub(1, Ordering::Relaxed);
+        return false;
+    }
+    unsafe { *TARGETS.slots[index].get() = Some(target) };
+    // Publish in claim order, so every published slot has been written.
+    while TARGETS
+        .published
+        .compare_exchange_weak(index, index + 1, Ordering::Release, Ordering::Relaxed)
+        .is_err()
+    {
+        core::hint::spin_loop();
+    }
+    true
+}
+
+/// Wipes every target registered with [`register_emergency_wipe`]. Async-signal-safe.
+pub fn emergency_wipe_all() {
+    let published = TARGETS.published.load(Ordering::Acquire);
+    for slot in &TARGETS.slots[..published] {
+        if let Some(target) = unsafe { *slot.get() } {
+            target.emergency_wipe();
+        }
+    }
+}
+
+/// Makes the process wipe every registered target when it exits through `exit` or by returning
+/// from `main`, so blocks that are never freed (leaked boxes, statics, `mem::forget`) do not
+/// outlive it. Returns `ENOMEM`, the only way `atexit` can fail, if the hook could not be
+/// installed.
+///
+/// `atexit` hooks run in reverse order of installation; install this one early so it runs
+/// after hooks that might still read the wiped memory. Calling it again does nothing.
+#[cfg(all(feature = "std", unix))]
+pub fn install_exit_wipe() -> Result<(), i32> {
+    use core::sync::atomic::AtomicBool;
+
+    static INSTALLED: AtomicBool = AtomicBool::new(false);
+
+    extern "C" fn wipe_at_exit() {
+        emergency_wipe_all();
+    }
+
+    if INSTALLED.swap(true, Ordering::AcqRel) {
+        return Ok(());
+    }
+    if unsafe { libc::atexit(wipe_at_exit) } != 0 {
+        INSTALLED.store(false, Ordering::Release);
+        return Err(libc::ENOMEM);
+    }
+    Ok(())
+}
+
+/// Signals [`install_crash_wipe`] handles.
+#[cfg(all(feature = "std", unix))]
+const CRASH_SIGNALS: [libc::c_int; 4] = [libc::SIGSEGV, libc::SIGBUS, libc::SIGABRT, libc::SIGILL];
+
+/// Installs a handler for `SIGSEGV`, `SIGBUS`, `SIGABRT` and `SIGILL` that wipes every
+/// registered target and then re-raises the signal with its default action, so core dumps of
+/// crashes no longer contain the wiped memory.
+///
+/// The handler replaces any previous one for these signals, including the standard library's
+/// stack overflow reporter. It runs on the alternate signal stack when one is set up, so stack
+/// overflows still get wiped. Returns the `errno` of the first `sigaction` that failed.
+#[cfg(all(feature = "std", unix))]
+pub fn install_crash_wipe() -> Result<(), i32> {
+    extern "C" fn wipe_on_crash(signal: libc::c_int) {
+        emergency_wipe_all();
+        // `SA_RESETHAND` already restored the default action, and the signal stays blocked
+        // until the handler returns, at which point it is delivered again.
+        unsafe { libc::raise(signal) };
+    }
+
+    for signal in CRASH_SIGNALS {
+        unsafe {
+            let mut action: libc::sigaction = core::mem::zeroed();
+            action.sa_sigaction = wipe_on_crash as extern "C" fn(libc::c_int) as libc::sighandler_t;
+            action.sa_flags = libc::SA_RESETHAND | libc::SA_ONSTACK;
+            libc::sigemptyset(&mut action.sa_mask);
+            if libc::sigaction(signal, &action, core::ptr::null_mut()) != 0 {
+                return Err(crate::unix::errno());
+            }
+        }
+    }
+    Ok(())
+}
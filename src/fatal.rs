@@ -0,0 +1,25 @@
+use core::fmt::{self, Write};
+
+/// Formats into a fixed buffer, dropping whatever does not fit.
+struct StackBuf {
+    buf: [u8; 256],
+    len: usize,
+}
+
+impl Write for StackBuf {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let n = s.len().min(self.buf.len() - self.len);
+        self.buf[self.len..self.len + n].copy_from_slice(&s.as_bytes()[..n]);
+        self.len += n;
+        Ok(())
+    }
+}
+
+/// Writes a diagnostic line to stderr and aborts, without allocating or taking locks, so it can
+/// be called from inside the allocator.
+pub(crate) fn abort(args: fmt::Arguments<'_>) -> ! {
+    let mut message = StackBuf { buf: [0; 256], len: 0 };
+    let _ = writeln!(message, "zeroize_alloc: {args}");
+    unsafe { libc::write(2, message.buf.as_ptr() as *const _, message.len) };
+    std::process::abort()
+}
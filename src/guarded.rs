@@ -0,0 +1,115 @@
+use core::alloc::{AllocError, Allocator, GlobalAlloc, Layout};
+use core::ptr::NonNull;
+
+use crate::unix::{exclude_from_dumps, page_size};
+use crate::{Backend, WideVolatile, WipeStrategy};
+
+/// Gives every allocation its own mapping with `PROT_NONE` guard pages on both sides, like
+/// libsodium's `sodium_malloc`.
+///
+/// Blocks end exactly where the trailing guard page begins (give or take alignment padding), so
+/// overrunning a secret faults immediately instead of reading or corrupting neighbouring heap
+/// data. The pages in between are `mlock`ed when the limit allows it and excluded from core
+/// dumps. Freeing wipes the block and unmaps everything.
+///
+/// Each allocation costs at least three pages and a few system calls, so this is meant for
+/// a handful of long-lived secrets. Alignments above the page size are not supported.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct GuardedAllocator;
+
+/// Where a block of a given layout sits in its mapping.
+struct Placement {
+    page: usize,
+    /// Size of the accessible pages between the guards.
+    data_len: usize,
+    /// Distance from the start of the block to the trailing guard page.
+    span: usize,
+}
+
+impl Placement {
+    fn of(layout: Layout) -> Option<Self> {
+        let page = page_size();
+        if layout.align() > page {
+            return None;
+        }
+        Some(Self {
+            page,
+            data_len: layout.size().div_ceil(page).max(1) * page,
+            span: layout.size().div_ceil(layout.align()) * layout.align(),
+        })
+    }
+
+    fn map_len(&self) -> usize {
+        self.data_len + 2 * self.page
+    }
+}
+
+unsafe impl GlobalAlloc for GuardedAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let Some(placement) = Placement::of(layout) else {
+            return core::ptr::null_mut();
+        };
+        let map = libc::mmap(
+            core::ptr::null_mut(),
+            placement.map_len(),
+            libc::PROT_NONE,
+            libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+            -1,
+            0,
+        );
+        if map == libc::MAP_FAILED {
+            return core::ptr::null_mut();
+        }
+        let data = (map as *mut u8).add(placement.page);
+        if libc::mprotect(data as *mut _, placement.data_len, libc::PROT_READ | libc::PROT_WRITE)
+            != 0
+        {
+            libc::munmap(map, placement.map_len());
+            return core::ptr::null_mut();
+        }
+        // Locking is best effort: RLIMIT_MEMLOCK is easily exhausted one mapping at a time.
+        libc::mlock(data as *const _, placement.data_len);
+        exclude_from_dumps(data as *mut _, placement.data_len);
+        data.add(placement.data_len - placement.span)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        let Some(placement) = Placement::of(layout) else {
+            return;
+        };
+        WideVolatile.wipe(ptr, placement.span);
+        let data = ptr.add(placement.span).sub(placement.data_len);
+        libc::munlock(data as *const _, placement.data_len);
+        libc::munmap(data.sub(placement.page) as *mut _, placement.map_len());
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        // Fresh anonymous mappings are zero-filled.
+        self.alloc(layout)
+    }
+}
+
+unsafe impl Allocator for GuardedAllocator {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        if layout.size() == 0 {
+            let dangling = unsafe {
+                NonNull::new_unchecked(core::ptr::without_provenance_mut(layout.align()))
+            };
+            return Ok(NonNull::slice_from_raw_parts(dangling, 0));
+        }
+        let ptr = NonNull::new(unsafe { self.alloc(layout) }).ok_or(AllocError)?;
+        Ok(NonNull::slice_from_raw_parts(ptr, layout.size()))
+    }
+
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.allocate(layout)
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        if layout.size() != 0 {
+            self.dealloc(ptr.as_ptr(), layout);
+        }
+    }
+}
+
+unsafe impl Backend for GuardedAllocator {}
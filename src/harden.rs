@@ -0,0 +1,74 @@
+use crate::unix::errno;
+
+/// What [`harden_process`] did. Each step holds `Ok(())` if it succeeded, the `errno` of the
+/// failing call otherwise, or `None` if it was not attempted because it was not requested or
+/// the platform does not support it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HardenReport {
+    /// Setting `RLIMIT_CORE` to zero, so crashes do not write core dumps.
+    pub core_limit: Option<Result<(), i32>>,
+    /// Clearing `PR_SET_DUMPABLE` (Linux and Android), which also keeps other processes of the
+    /// same user from attaching with `ptrace` or reading `/proc/<pid>/mem`.
+    pub not_dumpable: Option<Result<(), i32>>,
+    /// Locking all current and future pages with `mlockall`, so nothing is swapped out.
+    pub lock_all: Option<Result<(), i32>>,
+}
+
+impl HardenReport {
+    /// Whether every attempted step succeeded.
+    pub fn is_complete(&self) -> bool {
+        [self.core_limit, self.not_dumpable, self.lock_all]
+            .iter()
+            .all(|step| !matches!(step, Some(Err(_))))
+    }
+}
+
+/// Keeps the process's memory out of core dumps, debuggers and (with `lock_all`) swap, as a
+/// companion to the wiping allocators, which cannot help once memory has been copied out of
+/// the process.
+///
+/// Every step is attempted even if an earlier one failed. `mlockall` locks every page the
+/// process ever maps, so `RLIMIT_MEMLOCK` must allow for the whole heap.
+pub fn harden_process(lock_all: bool) -> HardenReport {
+    HardenReport {
+        core_limit: Some(disable_core_dumps()),
+        not_dumpable: clear_dumpable(),
+        lock_all: if lock_all { lock_all_pages() } else { None },
+    }
+}
+
+fn check(result: libc::c_int) -> Result<(), i32> {
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(errno())
+    }
+}
+
+fn disable_core_dumps() -> Result<(), i32> {
+    let limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    check(unsafe { libc::setrlimit(libc::RLIMIT_CORE, &limit) })
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn clear_dumpable() -> Option<Result<(), i32>> {
+    Some(check(unsafe { libc::prctl(libc::PR_SET_DUMPABLE, 0 as libc::c_ulong) }))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn clear_dumpable() -> Option<Result<(), i32>> {
+    None
+}
+
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "freebsd"))]
+fn lock_all_pages() -> Option<Result<(), i32>> {
+    Some(check(unsafe { libc::mlockall(libc::MCL_CURRENT | libc::MCL_FUTURE) }))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "freebsd")))]
+fn lock_all_pages() -> Option<Result<(), i32>> {
+    None
+}
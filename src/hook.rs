@@ -0,0 +1,24 @@
+use core::alloc::Layout;
+
+/// Callbacks the wrappers run around the wipe of every block they free, for auditing,
+/// accounting or extra scrubbing without another allocator layer.
+///
+/// Both methods run on every deallocation that is not a detected double free, whether or not
+/// the block is actually wiped (see [`WipePolicy`](crate::WipePolicy) and
+/// [`set_enabled`](crate::set_enabled)), and before the block is handed to the inner allocator.
+/// They run inside the allocator, so they must not allocate through the wrapper they are
+/// attached to.
+///
+/// When a block shrinks in place, they also run around the wipe of the tail it gives up, with
+/// the address of the tail and a layout of its length and an alignment of 1.
+pub trait DeallocHook: Sync {
+    /// Runs before the block at `ptr`, allocated with `layout`, is wiped.
+    fn before_wipe(&self, ptr: *mut u8, layout: Layout) {
+        let _ = (ptr, layout);
+    }
+
+    /// Runs after the block at `ptr`, allocated with `layout`, was wiped.
+    fn after_wipe(&self, ptr: *mut u8, layout: Layout) {
+        let _ = (ptr, layout);
+    }
+}
@@ -1,56 +1,505 @@
 #![no_std]
 #![feature(allocator_api)]
 
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
 use core::alloc::{GlobalAlloc, Allocator, Layout};
+use core::marker::PhantomData;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use wiper::Wiper;
+
+#[cfg(all(feature = "std", unix))]
+mod arena;
+mod backend;
+mod double_free;
+mod emergency;
+#[cfg(feature = "std")]
+mod fatal;
+#[cfg(all(feature = "std", unix))]
+mod guarded;
+#[cfg(all(feature = "std", unix))]
+mod harden;
+mod hook;
+mod policy;
+#[cfg(feature = "std")]
+mod prometheus;
+mod quarantine;
+mod redzone;
+mod registry;
+#[cfg(feature = "std")]
+mod scope;
+mod spin;
+mod stats;
+mod trace;
+#[cfg(all(feature = "std", unix))]
+mod unix;
+mod wipe;
+mod wiper;
+
+#[cfg(all(feature = "std", unix))]
+pub use arena::{ArenaError, SecureArena};
+pub use backend::{Backend, BackendOf, NoBackend, OwnsAddress, UseBackend};
+pub use double_free::{DoubleFreeHandler, DoubleFreeTracker};
+#[cfg(all(feature = "std", unix))]
+pub use emergency::{install_crash_wipe, install_exit_wipe};
+pub use emergency::{emergency_wipe_all, register_emergency_wipe, EmergencyWipe};
+#[cfg(all(feature = "std", unix))]
+pub use guarded::GuardedAllocator;
+#[cfg(all(feature = "std", unix))]
+pub use harden::{harden_process, HardenReport};
+pub use hook::DeallocHook;
+pub use policy::{MinAlign, SizeRange, WipePolicy};
+#[cfg(feature = "std")]
+pub use prometheus::write_prometheus;
+pub use quarantine::{Quarantine, QuarantineDepth, UafHandler, UafReport};
+pub use redzone::{Redzone, RedzoneHandler, RedzoneReport};
+pub use registry::{Registry, RegistryFullHandler};
+#[cfg(feature = "std")]
+pub use scope::{in_sensitive_scope, sensitive_scope, SensitiveAllocator, SensitiveGuard};
+pub use stats::{Histogram, Stats, StatsSnapshot};
+pub use wipe::{MultiPass, Pass, VolatileBytes, WideVolatile, WipeStrategy};
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Turns wiping on or off for every wrapper in the process, without touching the
+/// `#[global_allocator]` static. Wiping is on at startup.
+///
+/// Every deallocation that happens after this call, i.e. on the same thread or on a thread
+/// that has synchronized with it, observes the new setting. Deallocations racing with the call
+/// may observe either setting.
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::SeqCst);
+}
+
+/// Whether wiping is turned on process-wide, see [`set_enabled`].
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::SeqCst)
+}
+
+pub struct ZeroizingGlobalAllocator<
+    Alloc: GlobalAlloc,
+    Wipe: WipeStrategy = VolatileBytes,
+    Back: BackendOf<Alloc> = NoBackend,
+> {
+    alloc: Alloc,
+    wiper: Wiper<Wipe>,
+    backend: PhantomData<Back>,
+}
+
+pub struct ZeroizingAllocator<
+    Alloc: Allocator,
+    Wipe: WipeStrategy = VolatileBytes,
+    Back: BackendOf<Alloc> = NoBackend,
+> {
+    alloc: Alloc,
+    wiper: Wiper<Wipe>,
+    backend: PhantomData<Back>,
+}
+
+/// Builder and switch methods both wrappers share.
+macro_rules! wrapper_settings {
+    () => {
+        /// Fills memory returned by non-zeroing allocations, and the new part of grown blocks,
+        /// with `pattern` (jemalloc uses `0xa5`), so reads of uninitialized memory stand out
+        /// instead of seeing the zeros left by the last wipe. Zeroing allocations keep returning
+        /// zeros.
+        pub const fn junk_fill(mut self, pattern: u8) -> Self {
+            self.wiper.junk = Some(pattern);
+            self
+        }
+
+        /// Only wipes blocks `policy` selects; all blocks are wiped by default.
+        pub const fn with_policy(mut self, policy: &'static dyn WipePolicy) -> Self {
+            self.wiper.policy = Some(policy);
+            self
+        }
+
+        /// Checks every freed block against the blocks `tracker` saw freed recently, so a
+        /// double free is reported before the block, which may have been handed out again, is
+        /// wiped.
+        ///
+        /// The tracker remembers at most its `N` most recently freed blocks (1024 by default),
+        /// and fewer when their addresses collide, so a double free separated by many other
+        /// frees can go unnoticed; pick a larger `N` to widen that window.
+        pub const fn track_double_free<const N: usize>(
+            mut self,
+            tracker: &'static DoubleFreeTracker<N>,
+        ) -> Self {
+            self.wiper.double_free = Some(tracker as &'static dyn double_free::FreedBlocks);
+            self
+        }
+
+        /// Records live blocks in `registry`, so they can be wiped before they are freed with
+        /// [`Registry::emergency_wipe_all`].
+        pub const fn with_registry(mut self, registry: &'static Registry) -> Self {
+            self.wiper.registry = Some(registry);
+            self
+        }
+
+        /// Counts allocations, deallocations and wiped bytes in `stats`.
+        pub const fn with_stats(mut self, stats: &'static Stats) -> Self {
+            self.wiper.stats = Some(stats);
+            self
+        }
+
+        /// Runs `hook` before and after the wipe of every freed block.
+        pub const fn with_hook(mut self, hook: &'static dyn DeallocHook) -> Self {
+            self.wiper.hook = Some(hook);
+            self
+        }
+
+        /// Turns wiping on or off for this wrapper only, with the same guarantees as the
+        /// process-wide [`set_enabled`]. A wrapper wipes when both switches are on.
+        pub fn set_enabled(&self, enabled: bool) {
+            self.wiper.enabled.store(enabled, Ordering::SeqCst);
+        }
+
+        /// Whether wiping is turned on for this wrapper, regardless of the process-wide switch.
+        pub fn is_enabled(&self) -> bool {
+            self.wiper.enabled.load(Ordering::SeqCst)
+        }
+
+        /// The wrapped allocator.
+        pub const fn inner(&self) -> &A {
+            &self.alloc
+        }
+    };
+}
+
+impl<A: GlobalAlloc> ZeroizingGlobalAllocator<A> {
+    /// Wraps `alloc`, zeroing with [`VolatileBytes`].
+    pub const fn new(alloc: A) -> Self {
+        Self::with_strategy(alloc, VolatileBytes)
+    }
+}
+
+impl<A: GlobalAlloc, W: WipeStrategy> ZeroizingGlobalAllocator<A, W> {
+    /// Wraps `alloc`, overwriting freed memory with `wipe`.
+    pub const fn with_strategy(alloc: A, wipe: W) -> Self {
+        Self {
+            alloc,
+            wiper: Wiper::new(wipe),
+            backend: PhantomData,
+        }
+    }
+}
+
+impl<A: GlobalAlloc + Backend> ZeroizingGlobalAllocator<A, VolatileBytes, UseBackend> {
+    /// Wraps `alloc` like [`new`](ZeroizingGlobalAllocator::new), but uses its [`Backend`]
+    /// implementation, so `realloc` can resize blocks in place instead of copying them
+    /// and [`wipe_usable_size`](Self::wipe_usable_size) can reach size-class slack.
+    pub const fn with_backend(alloc: A) -> Self {
+        Self::with_backend_and_strategy(alloc, VolatileBytes)
+    }
+}
+
+impl<A: GlobalAlloc + Backend, W: WipeStrategy> ZeroizingGlobalAllocator<A, W, UseBackend> {
+    /// Wraps `alloc` like [`with_backend`](ZeroizingGlobalAllocator::with_backend), overwriting
+    /// freed memory with `wipe`.
+    pub const fn with_backend_and_strategy(alloc: A, wipe: W) -> Self {
+        Self {
+            alloc,
+            wiper: Wiper::new(wipe),
+            backend: PhantomData,
+        }
+    }
+}
 
-pub struct ZeroizingGlobalAllocator<Alloc: GlobalAlloc>(pub Alloc);
+impl<A: GlobalAlloc, W: WipeStrategy, B: BackendOf<A>> ZeroizingGlobalAllocator<A, W, B> {
+    /// Wipes the whole block the inner allocator reserved, as reported by
+    /// [`Backend::usable_size`], instead of only `layout.size()` bytes. Only has an effect
+    /// with [`with_backend`](ZeroizingGlobalAllocator::with_backend).
+    pub const fn wipe_usable_size(mut self, enabled: bool) -> Self {
+        self.wiper.usable_size = enabled;
+        self
+    }
 
-pub struct ZeroizingAllocator<Alloc: Allocator>(pub Alloc);
+    wrapper_settings!();
 
-#[cfg_attr(feature = "aggressive-inline", inline)]
-unsafe fn zero(ptr: *mut u8, size: usize) {
-    for i in 0..size {
-        core::ptr::write_volatile(ptr.offset(i as isize), 0);
+    #[cfg_attr(feature = "aggressive-inline", inline(always))]
+    unsafe fn wipe_len(&self, ptr: *mut u8, layout: Layout) -> usize {
+        if self.wiper.usable_size {
+            B::usable_size(&self.alloc, ptr, layout)
+        } else {
+            layout.size()
+        }
     }
-    core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
 }
 
-unsafe impl<A> Allocator for ZeroizingAllocator<A>
+impl<A: Allocator, W: WipeStrategy, B: BackendOf<A>> ZeroizingAllocator<A, W, B> {
+    #[cfg_attr(feature = "aggressive-inline", inline(always))]
+    unsafe fn wipe_len(&self, ptr: core::ptr::NonNull<u8>, layout: Layout) -> usize {
+        if self.wiper.usable_size && layout.size() != 0 {
+            B::usable_size(&self.alloc, ptr.as_ptr(), layout)
+        } else {
+            layout.size()
+        }
+    }
+
+    /// In usable-size mode, trims a block handed out by the inner allocator to the length
+    /// `deallocate` is going to wipe, so no secret can end up in unwiped slack.
+    #[cfg_attr(feature = "aggressive-inline", inline(always))]
+    unsafe fn hand_out(
+        &self,
+        block: core::ptr::NonNull<[u8]>,
+        layout: Layout,
+    ) -> core::ptr::NonNull<[u8]> {
+        if !self.wiper.usable_size {
+            return block;
+        }
+        let len = block.len().min(self.wipe_len(block.cast(), layout));
+        core::ptr::NonNull::slice_from_raw_parts(block.cast(), len)
+    }
+
+    /// Moves the block to `new_layout`, in place if the inner allocator can, otherwise by
+    /// copying it and wiping the old block before it is released. An in-place shrink wipes the
+    /// tail it cuts off.
+    unsafe fn resize(
+        &self,
+        ptr: core::ptr::NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+        zeroed: bool,
+    ) -> Result<core::ptr::NonNull<[u8]>, core::alloc::AllocError> {
+        if old_layout.size() != 0
+            && new_layout.size() != 0
+            && old_layout.align() == new_layout.align()
+            && B::resize_in_place(&self.alloc, ptr.as_ptr(), old_layout, new_layout.size())
+        {
+            let usable = B::usable_size(&self.alloc, ptr.as_ptr(), new_layout);
+            self.wiper.wipe_tail(ptr.as_ptr(), old_layout, new_layout.size(), usable);
+            self.wiper.resized(ptr.as_ptr(), old_layout.size(), new_layout.size());
+            if new_layout.size() > old_layout.size() {
+                let added = ptr.as_ptr().add(old_layout.size());
+                let len = new_layout.size() - old_layout.size();
+                if zeroed {
+                    added.write_bytes(0, len);
+                } else {
+                    self.wiper.fill_junk(added, len);
+                }
+            }
+            return Ok(core::ptr::NonNull::slice_from_raw_parts(ptr, new_layout.size()));
+        }
+        let new_ptr = if zeroed {
+            self.alloc.allocate_zeroed(new_layout)?
+        } else {
+            self.alloc.allocate(new_layout)?
+        };
+        let new_ptr = self.hand_out(new_ptr, new_layout);
+        self.wiper.allocated(new_ptr.cast::<u8>().as_ptr(), new_layout.size(), new_ptr.len());
+        let kept = old_layout.size().min(new_layout.size());
+        core::ptr::copy_nonoverlapping(ptr.as_ptr(), new_ptr.cast::<u8>().as_ptr(), kept);
+        if !zeroed {
+            self.wiper.fill_junk(new_ptr.cast::<u8>().as_ptr().add(kept), new_ptr.len() - kept);
+        }
+        self.deallocate(ptr, old_layout);
+        Ok(new_ptr)
+    }
+}
+
+impl<A: Allocator> ZeroizingAllocator<A> {
+    /// Wraps `alloc`, zeroing with [`VolatileBytes`].
+    pub const fn new(alloc: A) -> Self {
+        Self::with_strategy(alloc, VolatileBytes)
+    }
+}
+
+impl<A: Allocator, W: WipeStrategy> ZeroizingAllocator<A, W> {
+    /// Wraps `alloc`, overwriting freed memory with `wipe`.
+    pub const fn with_strategy(alloc: A, wipe: W) -> Self {
+        Self {
+            alloc,
+            wiper: Wiper::new(wipe),
+            backend: PhantomData,
+        }
+    }
+}
+
+impl<A: Allocator + Backend> ZeroizingAllocator<A, VolatileBytes, UseBackend> {
+    /// Wraps `alloc` like [`new`](ZeroizingAllocator::new), but uses its [`Backend`]
+    /// implementation, so `grow` and `shrink` can resize blocks in place instead of copying them
+    /// and [`wipe_usable_size`](Self::wipe_usable_size) can reach size-class slack.
+    pub const fn with_backend(alloc: A) -> Self {
+        Self::with_backend_and_strategy(alloc, VolatileBytes)
+    }
+}
+
+impl<A: Allocator + Backend, W: WipeStrategy> ZeroizingAllocator<A, W, UseBackend> {
+    /// Wraps `alloc` like [`with_backend`](ZeroizingAllocator::with_backend), overwriting
+    /// freed memory with `wipe`.
+    pub const fn with_backend_and_strategy(alloc: A, wipe: W) -> Self {
+        Self {
+            alloc,
+            wiper: Wiper::new(wipe),
+            backend: PhantomData,
+        }
+    }
+}
+
+impl<A: Allocator, W: WipeStrategy, B: BackendOf<A>> ZeroizingAllocator<A, W, B> {
+    /// Wipes the whole block the inner allocator reserved, as reported by
+    /// [`Backend::usable_size`], instead of only `layout.size()` bytes. The usable size is
+    /// only known with [`with_backend`](ZeroizingAllocator::with_backend); otherwise it is
+    /// `layout.size()`.
+    ///
+    /// Either way, the slices this wrapper hands out are trimmed to at most that size, so
+    /// callers using the slack of an over-allocating inner allocator never write where the wipe
+    /// does not reach. Without a backend this trims every slice to `layout.size()`. Trimming is
+    /// used instead of wiping the length of the slice the inner allocator returned, which
+    /// `deallocate` cannot know: it is only given a layout, and callers may pass any size
+    /// between the requested one and that length.
+    pub const fn wipe_usable_size(mut self, enabled: bool) -> Self {
+        self.wiper.usable_size = enabled;
+        self
+    }
+
+    wrapper_settings!();
+}
+
+unsafe impl<A, W, B> Allocator for ZeroizingAllocator<A, W, B>
 where
-    A: Allocator
+    A: Allocator,
+    W: WipeStrategy,
+    B: BackendOf<A>,
 {
     #[cfg_attr(feature = "aggressive-inline", inline(always))]
     fn allocate(&self, layout: Layout) -> Result<core::ptr::NonNull<[u8]>, core::alloc::AllocError> {
-        self.0.allocate(layout)
+        let block = unsafe { self.hand_out(self.alloc.allocate(layout)?, layout) };
+        self.wiper.allocated(block.cast::<u8>().as_ptr(), layout.size(), block.len());
+        unsafe { self.wiper.fill_junk(block.cast::<u8>().as_ptr(), block.len()) };
+        Ok(block)
+    }
+
+    #[cfg_attr(feature = "aggressive-inline", inline(always))]
+    fn allocate_zeroed(
+        &self,
+        layout: Layout,
+    ) -> Result<core::ptr::NonNull<[u8]>, core::alloc::AllocError> {
+        let block = unsafe { self.hand_out(self.alloc.allocate_zeroed(layout)?, layout) };
+        self.wiper.allocated(block.cast::<u8>().as_ptr(), layout.size(), block.len());
+        Ok(block)
     }
 
     #[cfg_attr(feature = "aggressive-inline", inline(always))]
     unsafe fn deallocate(&self, ptr: core::ptr::NonNull<u8>, layout: Layout) {
-        zero(ptr.as_ptr(), layout.size());
-        // #[cfg(not(test))]
-        self.0.deallocate(ptr.clone(), layout);
+        if self.wiper.release(ptr.as_ptr(), layout, || self.wipe_len(ptr, layout)) {
+            // #[cfg(not(test))]
+            self.alloc.deallocate(ptr, layout);
+        }
+    }
+
+    /// Resizes in place only through [`Backend::resize_in_place`] of a wrapper built with
+    /// [`with_backend`](ZeroizingAllocator::with_backend), otherwise allocates a new block,
+    /// copies and wipes the old one. The inner allocator's own `grow` is never called, since
+    /// it could move the block without wiping the old one.
+    #[cfg_attr(feature = "aggressive-inline", inline(always))]
+    unsafe fn grow(
+        &self,
+        ptr: core::ptr::NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<core::ptr::NonNull<[u8]>, core::alloc::AllocError> {
+        self.resize(ptr, old_layout, new_layout, false)
+    }
+
+    #[cfg_attr(feature = "aggressive-inline", inline(always))]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: core::ptr::NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<core::ptr::NonNull<[u8]>, core::alloc::AllocError> {
+        self.resize(ptr, old_layout, new_layout, true)
+    }
+
+    /// Resizes in place only through [`Backend::resize_in_place`] of a wrapper built with
+    /// [`with_backend`](ZeroizingAllocator::with_backend), otherwise allocates a new block,
+    /// copies and wipes the old one. The inner allocator's own `shrink` is never called, since
+    /// it could move the block without wiping the old one.
+    #[cfg_attr(feature = "aggressive-inline", inline(always))]
+    unsafe fn shrink(
+        &self,
+        ptr: core::ptr::NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<core::ptr::NonNull<[u8]>, core::alloc::AllocError> {
+        self.resize(ptr, old_layout, new_layout, false)
     }
 }
 
-unsafe impl<A> GlobalAlloc for ZeroizingGlobalAllocator<A>
+unsafe impl<A, W, B> GlobalAlloc for ZeroizingGlobalAllocator<A, W, B>
 where
     A: GlobalAlloc,
+    W: WipeStrategy,
+    B: BackendOf<A>,
 {
     #[cfg_attr(feature = "aggressive-inline", inline(always))]
     unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
-        self.0.alloc(layout)
+        let ptr = self.alloc.alloc(layout);
+        if !ptr.is_null() {
+            self.wiper.allocated(ptr, layout.size(), layout.size());
+            self.wiper.fill_junk(ptr, layout.size());
+        }
+        ptr
     }
 
     #[cfg_attr(feature = "aggressive-inline", inline(always))]
     unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
-        zero(ptr, layout.size());
-        #[cfg(not(test))]
-        self.0.dealloc(ptr, layout);
+        if self.wiper.release(ptr, layout, || self.wipe_len(ptr, layout)) {
+            #[cfg(not(test))]
+            self.alloc.dealloc(ptr, layout);
+        }
     }
 
     #[cfg_attr(feature = "aggressive-inline", inline(always))]
     unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
-        self.0.alloc_zeroed(layout)
+        let ptr = self.alloc.alloc_zeroed(layout);
+        self.wiper.allocated(ptr, layout.size(), layout.size());
+        ptr
+    }
+
+    /// Resizes in place when the inner allocator can, otherwise moves the block and wipes the
+    /// old one.
+    #[cfg_attr(feature = "aggressive-inline", inline(always))]
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let kept = layout.size().min(new_size);
+        let new_layout = Layout::from_size_align_unchecked(new_size, layout.align());
+        if B::resize_in_place(&self.alloc, ptr, layout, new_size) {
+            let usable = B::usable_size(&self.alloc, ptr, new_layout);
+            self.wiper.wipe_tail(ptr, layout, new_size, usable);
+            self.wiper.resized(ptr, layout.size(), new_size);
+            self.wiper.fill_junk(ptr.add(kept), new_size - kept);
+            return ptr;
+        }
+        let new_ptr = self.alloc.alloc(new_layout);
+        if !new_ptr.is_null() {
+            self.wiper.allocated(new_ptr, new_size, new_size);
+            core::ptr::copy_nonoverlapping(ptr, new_ptr, kept);
+            self.wiper.fill_junk(new_ptr.add(kept), new_size - kept);
+            self.dealloc(ptr, layout);
+        }
+        new_ptr
+    }
+}
+
+unsafe impl<A: GlobalAlloc + OwnsAddress, W: WipeStrategy, B: BackendOf<A>> OwnsAddress
+    for ZeroizingGlobalAllocator<A, W, B>
+{
+    #[cfg_attr(feature = "aggressive-inline", inline(always))]
+    fn owns(&self, ptr: *const u8) -> bool {
+        self.alloc.owns(ptr)
+    }
+}
+
+unsafe impl<A: Allocator + OwnsAddress, W: WipeStrategy, B: BackendOf<A>> OwnsAddress
+    for ZeroizingAllocator<A, W, B>
+{
+    #[cfg_attr(feature = "aggressive-inline", inline(always))]
+    fn owns(&self, ptr: *const u8) -> bool {
+        self.alloc.owns(ptr)
     }
 }
 
@@ -61,7 +510,7 @@ mod test {
 
     #[global_allocator]
     static ALLOC: super::ZeroizingGlobalAllocator<std::alloc::System> =
-        super::ZeroizingGlobalAllocator(std::alloc::System);
+        super::ZeroizingGlobalAllocator::new(std::alloc::System);
 
     #[test]
     fn test_static() {
@@ -73,15 +522,946 @@ mod test {
         a.push(0xef);
         let ptr2: *const u8 = &a[0];
         assert_eq!(&[0xde, 0xad, 0xbe, 0xef], &a[..]);
+        // `ALLOC` has no backend, so growing always moves the block.
+        assert_ne!(ptr1, ptr2);
         assert_eq!(unsafe { ptr1.as_ref() }, Some(&0));
         drop(a);
         assert_eq!(unsafe { ptr2.as_ref() }, Some(&0));
     }
 
+    #[test]
+    fn test_realloc() {
+        use core::alloc::{GlobalAlloc, Layout};
+
+        let layout = Layout::from_size_align(64, 8).unwrap();
+        unsafe {
+            let ptr = ALLOC.alloc(layout);
+            ptr.write_bytes(0xff, layout.size());
+            let shrunk = ALLOC.realloc(ptr, layout, 16);
+            assert_ne!(shrunk, ptr);
+            assert!(core::slice::from_raw_parts(shrunk, 16).iter().all(|&b| b == 0xff));
+            assert!(core::slice::from_raw_parts(ptr, 64).iter().all(|&b| b == 0));
+
+            let layout = Layout::from_size_align(16, 8).unwrap();
+            let grown = ALLOC.realloc(shrunk, layout, 1 << 20);
+            assert_ne!(grown, shrunk);
+            assert!(core::slice::from_raw_parts(grown, 16).iter().all(|&b| b == 0xff));
+            assert!(core::slice::from_raw_parts(shrunk, 16).iter().all(|&b| b == 0));
+        }
+    }
+
+    #[test]
+    #[cfg(all(feature = "std", target_os = "linux"))]
+    fn test_realloc_in_place() {
+        use core::alloc::{GlobalAlloc, Layout};
+
+        let alloc = super::ZeroizingGlobalAllocator::with_backend(std::alloc::System);
+        let layout = Layout::from_size_align(64, 8).unwrap();
+        unsafe {
+            let ptr = alloc.alloc(layout);
+            ptr.write_bytes(0xff, layout.size());
+            let shrunk = alloc.realloc(ptr, layout, 48);
+            assert_eq!(shrunk, ptr);
+            assert!(core::slice::from_raw_parts(ptr, 48).iter().all(|&b| b == 0xff));
+            assert!(core::slice::from_raw_parts(ptr.add(48), 16).iter().all(|&b| b == 0));
+
+            let layout = Layout::from_size_align(48, 8).unwrap();
+            let regrown = alloc.realloc(shrunk, layout, 64);
+            assert_eq!(regrown, ptr);
+
+            // Shrinking far below the size class moves, so `malloc` gets the memory back.
+            let layout = Layout::from_size_align(64, 8).unwrap();
+            let huge = Layout::from_size_align(1 << 20, 8).unwrap();
+            let big = alloc.realloc(regrown, layout, huge.size());
+            big.write_bytes(0xff, huge.size());
+            let small = alloc.realloc(big, huge, 16);
+            assert_ne!(small, big);
+            assert!(core::slice::from_raw_parts(small, 16).iter().all(|&b| b == 0xff));
+            assert!(core::slice::from_raw_parts(big, huge.size()).iter().all(|&b| b == 0));
+            alloc.dealloc(small, Layout::from_size_align(16, 8).unwrap());
+        }
+    }
+
+    struct Fill(u8);
+
+    impl super::WipeStrategy for Fill {
+        unsafe fn wipe(&self, ptr: *mut u8, len: usize) {
+            for i in 0..len {
+                core::ptr::write_volatile(ptr.add(i), self.0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_custom_strategy() {
+        use core::alloc::{GlobalAlloc, Layout};
+
+        let alloc = super::ZeroizingGlobalAllocator::with_strategy(std::alloc::System, Fill(0x5a));
+        let layout = Layout::new::<[u8; 16]>();
+        unsafe {
+            let ptr = alloc.alloc(layout);
+            ptr.write_bytes(0xff, layout.size());
+            alloc.dealloc(ptr, layout);
+            assert!(core::slice::from_raw_parts(ptr, layout.size()).iter().all(|&b| b == 0x5a));
+        }
+    }
+
+    #[test]
+    fn test_wide_volatile() {
+        use super::WipeStrategy;
+
+        for offset in 0..40 {
+            for len in 0..120 {
+                let mut buf = [0xffu8; 200];
+                unsafe { super::WideVolatile.wipe(buf.as_mut_ptr().add(offset), len) };
+                assert!(buf[..offset].iter().all(|&b| b == 0xff));
+                assert!(buf[offset..offset + len].iter().all(|&b| b == 0));
+                assert!(buf[offset + len..].iter().all(|&b| b == 0xff));
+            }
+        }
+    }
+
+    #[test]
+    fn test_multi_pass() {
+        use super::{MultiPass, Pass, WipeStrategy};
+
+        let mut buf = [0u8; 100];
+        unsafe {
+            MultiPass::new(&[Pass::Byte(0xff), Pass::Random, Pass::Byte(0x11)])
+                .wipe(buf.as_mut_ptr(), 99);
+        }
+        assert!(buf[..99].iter().all(|&b| b == 0x11));
+        assert_eq!(buf[99], 0);
+
+        unsafe { MultiPass::new(&[Pass::Random]).wipe(buf.as_mut_ptr(), 99) };
+        assert!(buf[..99].iter().any(|&b| b != 0x11));
+        assert_eq!(buf[99], 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one pass")]
+    fn test_multi_pass_empty() {
+        super::MultiPass::new(&[]);
+    }
+
+    #[test]
+    fn test_instance_switch() {
+        use core::alloc::{GlobalAlloc, Layout};
+
+        let alloc = super::ZeroizingGlobalAllocator::new(std::alloc::System);
+        let layout = Layout::from_size_align(16, 8).unwrap();
+        unsafe {
+            alloc.set_enabled(false);
+            let ptr = alloc.alloc(layout);
+            ptr.write_bytes(0xff, 16);
+            alloc.dealloc(ptr, layout);
+            assert!(core::slice::from_raw_parts(ptr, 16).iter().all(|&b| b == 0xff));
+
+            alloc.set_enabled(true);
+            let ptr = alloc.alloc(layout);
+            ptr.write_bytes(0xff, 16);
+            alloc.dealloc(ptr, layout);
+            assert!(core::slice::from_raw_parts(ptr, 16).iter().all(|&b| b == 0));
+        }
+    }
+
+    #[test]
+    fn test_policy() {
+        use core::alloc::{GlobalAlloc, Layout};
+
+        static LARGE: super::SizeRange = super::SizeRange::at_least(64);
+        let alloc = super::ZeroizingGlobalAllocator::new(std::alloc::System).with_policy(&LARGE);
+        unsafe {
+            for (size, wiped) in [(16, false), (64, true)] {
+                let layout = Layout::from_size_align(size, 8).unwrap();
+                let ptr = alloc.alloc(layout);
+                ptr.write_bytes(0xff, size);
+                alloc.dealloc(ptr, layout);
+                let expected = if wiped { 0 } else { 0xff };
+                assert!(core::slice::from_raw_parts(ptr, size).iter().all(|&b| b == expected));
+            }
+        }
+    }
+
+    /// Bump-allocates from a fixed buffer and never reuses memory.
+    #[cfg(feature = "std")]
+    struct Bump {
+        buf: core::cell::UnsafeCell<[u8; 4096]>,
+        next: core::sync::atomic::AtomicUsize,
+    }
+
+    #[cfg(feature = "std")]
+    unsafe impl Sync for Bump {}
+
+    #[cfg(feature = "std")]
+    unsafe impl core::alloc::GlobalAlloc for Bump {
+        unsafe fn alloc(&self, layout: core::alloc::Layout) -> *mut u8 {
+            let base = self.buf.get() as *mut u8;
+            let start = self.next.load(core::sync::atomic::Ordering::Relaxed);
+            let offset = start + base.add(start).align_offset(layout.align());
+            if offset + layout.size() > 4096 {
+                return core::ptr::null_mut();
+            }
+            self.next.store(offset + layout.size(), core::sync::atomic::Ordering::Relaxed);
+            base.add(offset)
+        }
+
+        unsafe fn dealloc(&self, _ptr: *mut u8, _layout: core::alloc::Layout) {}
+    }
+
+    #[cfg(feature = "std")]
+    unsafe impl super::OwnsAddress for Bump {
+        fn owns(&self, ptr: *const u8) -> bool {
+            let base = self.buf.get() as usize;
+            (base..base + 4096).contains(&(ptr as usize))
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_sensitive_scope() {
+        use super::OwnsAddress;
+        use core::alloc::{GlobalAlloc, Layout};
+        use core::sync::atomic::AtomicUsize;
+
+        let bump = Bump {
+            buf: core::cell::UnsafeCell::new([0; 4096]),
+            next: AtomicUsize::new(0),
+        };
+        let alloc = super::SensitiveAllocator::new(
+            std::alloc::System,
+            super::ZeroizingGlobalAllocator::new(bump),
+        );
+        let bump = alloc.secure().inner();
+        let layout = Layout::from_size_align(32, 8).unwrap();
+        unsafe {
+            let plain = alloc.alloc(layout);
+            let secret = super::sensitive_scope(|| {
+                assert!(super::in_sensitive_scope());
+                alloc.alloc(layout)
+            });
+            assert!(!super::in_sensitive_scope());
+            assert!(!bump.owns(plain));
+            assert!(bump.owns(secret));
+            secret.write_bytes(0xff, 32);
+
+            // Routed by address even though the scope has ended.
+            alloc.dealloc(secret, layout);
+            assert!(core::slice::from_raw_parts(secret, 32).iter().all(|&b| b == 0));
+            alloc.dealloc(plain, layout);
+        }
+    }
+
+    #[test]
+    #[cfg(all(feature = "std", unix))]
+    fn test_secure_arena() {
+        use super::{Backend, OwnsAddress};
+        use core::alloc::{GlobalAlloc, Layout};
+
+        // Small enough to `mlock` under the 64 KiB `RLIMIT_MEMLOCK` containers default to.
+        let arena = super::SecureArena::new(1 << 14);
+        assert_eq!(arena.init(), Ok(()));
+        let layout = Layout::from_size_align(40, 8).unwrap();
+        unsafe {
+            let a = arena.alloc(layout);
+            let b = arena.alloc(Layout::from_size_align(64, 64).unwrap());
+            assert!(arena.owns(a) && arena.owns(b));
+            assert_eq!(b as usize % 64, 0);
+            assert!(!arena.owns(&layout as *const Layout as *const u8));
+
+            a.write_bytes(0xff, 40);
+            assert!(!arena.resize_in_place(a, layout, 4096));
+            arena.dealloc(a, layout);
+            assert!(core::slice::from_raw_parts(a, 48).iter().all(|&b| b == 0));
+            // The freed granules are reused first.
+            assert_eq!(arena.alloc_zeroed(layout), a);
+        }
+
+        let mut secrets = Vec::new_in(&arena);
+        secrets.extend_from_slice(&[0x42u8; 1000]);
+        assert!(arena.owns(secrets.as_ptr()));
+        let too_big = Layout::from_size_align(1 << 17, 8).unwrap();
+        assert!(core::alloc::Allocator::allocate(&arena, too_big).is_err());
+    }
+
+    #[test]
+    #[cfg(all(feature = "std", unix))]
+    fn test_guarded_allocator() {
+        use core::alloc::{GlobalAlloc, Layout};
+
+        let page = unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize };
+        let layout = Layout::from_size_align(100, 8).unwrap();
+        unsafe {
+            let ptr = super::GuardedAllocator.alloc_zeroed(layout);
+            assert_eq!((ptr as usize + 104) % page, 0);
+            assert!(core::slice::from_raw_parts(ptr, 100).iter().all(|&b| b == 0));
+            ptr.write_bytes(0xff, 100);
+
+            // Touching the guard page right behind the block kills the process.
+            let pid = libc::fork();
+            if pid == 0 {
+                core::ptr::read_volatile(ptr.add(104));
+                libc::_exit(0);
+            }
+            let mut status = 0;
+            assert_eq!(libc::waitpid(pid, &mut status, 0), pid);
+            assert!(libc::WIFSIGNALED(status));
+            assert!([libc::SIGSEGV, libc::SIGBUS].contains(&libc::WTERMSIG(status)));
+
+            super::GuardedAllocator.dealloc(ptr, layout);
+            let page_start = (ptr as usize / page * page) as *mut libc::c_void;
+            assert_eq!(libc::msync(page_start, page, libc::MS_ASYNC), -1);
+        }
+    }
+
+    #[test]
+    fn test_redzone() {
+        use core::alloc::{GlobalAlloc, Layout};
+        use std::sync::Mutex;
+
+        static DAMAGED: Mutex<Vec<(Vec<isize>, usize)>> = Mutex::new(Vec::new());
+
+        fn record(_ptr: *mut u8, _layout: Layout, report: &super::RedzoneReport<'_>) {
+            DAMAGED.lock().unwrap().push((report.damaged.to_vec(), report.total));
+        }
+
+        let zeroizing = super::ZeroizingGlobalAllocator::new(std::alloc::System);
+        let alloc = super::Redzone::new(zeroizing, record);
+        let layout = Layout::from_size_align(10, 4).unwrap();
+        unsafe {
+            let ptr = alloc.alloc(layout);
+            ptr.write_bytes(0xff, 10);
+            alloc.dealloc(ptr, layout);
+            assert!(DAMAGED.lock().unwrap().is_empty());
+
+            let ptr = alloc.alloc(layout);
+            ptr.sub(1).write(0);
+            ptr.add(10).write(0);
+            ptr.add(12).write(0);
+            alloc.dealloc(ptr, layout);
+            assert_eq!(&DAMAGED.lock().unwrap()[..], &[(std::vec![-1, 10, 12], 3)]);
+            // The canaries were checked before the whole padded block was wiped.
+            assert!(core::slice::from_raw_parts(ptr.sub(16), 42).iter().all(|&b| b == 0));
+        }
+    }
+
+    #[test]
+    fn test_double_free() {
+        use core::alloc::{GlobalAlloc, Layout};
+        use std::sync::Mutex;
+
+        static FREED_TWICE: Mutex<Vec<usize>> = Mutex::new(Vec::new());
+        static TRACKER: super::DoubleFreeTracker =
+            super::DoubleFreeTracker::with_handler(|ptr, _layout| {
+                FREED_TWICE.lock().unwrap().push(ptr as usize)
+            });
+
+        let alloc =
+            super::ZeroizingGlobalAllocator::new(std::alloc::System).track_double_free(&TRACKER);
+        let layout = Layout::from_size_align(16, 8).unwrap();
+        unsafe {
+            let ptr = alloc.alloc(layout);
+            alloc.dealloc(ptr, layout);
+            assert!(FREED_TWICE.lock().unwrap().is_empty());
+
+            // Whatever now lives in the block is left alone.
+            ptr.write_bytes(0xee, 16);
+            alloc.dealloc(ptr, layout);
+            assert_eq!(&FREED_TWICE.lock().unwrap()[..], &[ptr as usize]);
+            assert!(core::slice::from_raw_parts(ptr, 16).iter().all(|&b| b == 0xee));
+        }
+
+        // A larger tracker still remembers a block after a thousand other frees.
+        static WIDE: super::DoubleFreeTracker<{ 1 << 16 }> =
+            super::DoubleFreeTracker::with_handler(|ptr, _layout| {
+                FREED_TWICE.lock().unwrap().push(ptr as usize)
+            });
+        let alloc =
+            super::ZeroizingGlobalAllocator::new(std::alloc::System).track_double_free(&WIDE);
+        unsafe {
+            let ptr = alloc.alloc(layout);
+            alloc.dealloc(ptr, layout);
+            for _ in 0..1000 {
+                alloc.dealloc(alloc.alloc(layout), layout);
+            }
+            alloc.dealloc(ptr, layout);
+            assert_eq!(FREED_TWICE.lock().unwrap().last(), Some(&(ptr as usize)));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_registry() {
+        use core::alloc::{GlobalAlloc, Layout};
+
+        static REGISTRY: super::Registry = super::Registry::new();
+
+        let alloc =
+            super::ZeroizingGlobalAllocator::new(std::alloc::System).with_registry(&REGISTRY);
+        let layout = Layout::from_size_align(32, 8).unwrap();
+        unsafe {
+            let kept = alloc.alloc(layout);
+            let freed = alloc.alloc(layout);
+            assert_eq!(REGISTRY.len(), 2);
+            alloc.dealloc(freed, layout);
+            assert_eq!(REGISTRY.len(), 1);
+
+            kept.write_bytes(0xff, 32);
+            freed.write_bytes(0xff, 32);
+            REGISTRY.emergency_wipe_all();
+            assert!(core::slice::from_raw_parts(kept, 32).iter().all(|&b| b == 0));
+            assert!(core::slice::from_raw_parts(freed, 32).iter().all(|&b| b == 0xff));
+            alloc.dealloc(kept, layout);
+            assert!(REGISTRY.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_registry_growth() {
+        use core::sync::atomic::{AtomicUsize, Ordering};
+
+        static FULL: AtomicUsize = AtomicUsize::new(0);
+        static REGISTRY: super::Registry = super::Registry::with_handler(|_ptr, _size| {
+            FULL.fetch_add(1, Ordering::Relaxed);
+        });
+
+        // The addresses are never dereferenced, since nothing is wiped.
+        let blocks = (1..=100_000).map(|i| (i * 16) as *mut u8);
+        for ptr in blocks.clone() {
+            REGISTRY.insert(ptr, 16);
+        }
+        assert_eq!(REGISTRY.untracked(), FULL.load(Ordering::Relaxed));
+        assert_eq!(REGISTRY.len() + REGISTRY.untracked(), 100_000);
+        if cfg!(all(feature = "std", unix)) {
+            assert_eq!(REGISTRY.untracked(), 0);
+        } else {
+            assert!(REGISTRY.untracked() > 0);
+        }
+
+        // Removing everything leaves freed slots behind, which new blocks reuse.
+        for ptr in blocks {
+            REGISTRY.remove(ptr);
+        }
+        assert!(REGISTRY.is_empty());
+        for i in 1..=8000 {
+            REGISTRY.insert((i * 16 + 8) as *mut u8, 16);
+        }
+        assert_eq!(REGISTRY.len(), 8000);
+        assert_eq!(REGISTRY.untracked(), FULL.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn test_stats() {
+        use core::alloc::{GlobalAlloc, Layout};
+
+        static STATS: super::Stats = super::Stats::new();
+
+        let alloc = super::ZeroizingGlobalAllocator::new(std::alloc::System).with_stats(&STATS);
+        let large = Layout::from_size_align(64, 8).unwrap();
+        let small = Layout::from_size_align(32, 8).unwrap();
+        unsafe {
+            let a = alloc.alloc(large);
+            let b = alloc.alloc_zeroed(small);
+            alloc.dealloc(a, large);
+            let snapshot = STATS.snapshot();
+            assert_eq!(snapshot.allocations, 2);
+            assert_eq!(snapshot.deallocations, 1);
+            assert_eq!(snapshot.bytes_wiped, 64);
+            assert_eq!(snapshot.live_bytes, 32);
+            assert_eq!(snapshot.peak_live_bytes, 96);
+            assert_eq!(snapshot.dealloc_sizes.buckets[7], 1);
+            assert_eq!(snapshot.dealloc_sizes.total(), 1);
+
+            alloc.dealloc(b, small);
+            let snapshot = STATS.snapshot();
+            assert_eq!(snapshot.bytes_wiped, 96);
+            assert_eq!(snapshot.live_bytes, 0);
+            assert_eq!(snapshot.peak_live_bytes, 96);
+            assert_eq!(snapshot.wipe_sizes.buckets[6], 32);
+            assert_eq!(snapshot.wipe_sizes.buckets[7], 64);
+            assert_eq!(snapshot.wipe_sizes.total(), 96);
+
+            // A shrink that moves the block wipes the old one once, tail included.
+            let a = alloc.alloc(large);
+            let c = alloc.realloc(a, large, 16);
+            assert_ne!(c, a);
+            assert_eq!(STATS.snapshot().bytes_wiped, 160);
+            alloc.dealloc(c, Layout::from_size_align(16, 8).unwrap());
+        }
+
+        assert_eq!(super::Histogram::bucket(0), 0);
+        assert_eq!(super::Histogram::bucket(1), 1);
+        assert_eq!(super::Histogram::bucket(4096), 13);
+        assert_eq!(super::Histogram::upper_bound(13), 8191);
+        assert_eq!(super::Histogram::upper_bound(super::Histogram::BUCKETS - 1), usize::MAX);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_prometheus() {
+        use core::alloc::{GlobalAlloc, Layout};
+        use std::string::String;
+
+        static STATS: super::Stats = super::Stats::new();
+
+        let quarantine = super::Quarantine::new(std::alloc::System, 1024);
+        let alloc = super::ZeroizingGlobalAllocator::new(quarantine).with_stats(&STATS);
+        let layout = Layout::from_size_align(48, 8).unwrap();
+        unsafe {
+            let ptr = alloc.alloc(layout);
+            alloc.dealloc(ptr, layout);
+            // Under test, the wrapper does not pass freed blocks on by itself.
+            alloc.inner().dealloc(ptr, layout);
+        }
+        let depth = alloc.inner().depth();
+        assert_eq!(depth, super::QuarantineDepth { blocks: 1, bytes: 48 });
+
+        let mut out = String::new();
+        super::write_prometheus(&mut out, &STATS.snapshot(), Some(depth)).unwrap();
+        for line in [
+            "# TYPE zeroize_alloc_wiped_bytes_total counter",
+            "zeroize_alloc_wiped_bytes_total 48",
+            "zeroize_alloc_live_bytes 0",
+            "# TYPE zeroize_alloc_wipe_duration_seconds histogram",
+            "zeroize_alloc_wipe_duration_seconds_bucket{le=\"+Inf\"} 1",
+            "zeroize_alloc_wipe_duration_seconds_count 1",
+            "zeroize_alloc_quarantine_blocks 1",
+            "zeroize_alloc_quarantine_bytes 48",
+        ] {
+            assert!(out.lines().any(|l| l == line), "{line:?} missing from\n{out}");
+        }
+        let buckets = out.lines().filter(|l| l.contains("_bucket{")).map(|l| {
+            l.rsplit(' ').next().unwrap().parse::<u64>().unwrap()
+        });
+        assert!(buckets.collect::<Vec<_>>().windows(2).all(|w| w[0] <= w[1]));
+    }
+
+    #[test]
+    #[cfg(feature = "tracing")]
+    fn test_tracing() {
+        use core::alloc::{GlobalAlloc, Layout};
+        use core::sync::atomic::{AtomicUsize, Ordering};
+        use tracing::span::{Attributes, Id, Record};
+        use tracing::{Event, Metadata};
+
+        static POLICY: super::SizeRange = super::SizeRange::at_least(1);
+        static TRACED: super::ZeroizingGlobalAllocator<std::alloc::System> =
+            super::ZeroizingGlobalAllocator::new(std::alloc::System).with_policy(&POLICY);
+
+        unsafe fn churn(size: usize) {
+            let layout = Layout::from_size_align(size, 8).unwrap();
+            TRACED.dealloc(TRACED.alloc(layout), layout);
+        }
+
+        /// Counts events, and frees a block through `TRACED` for each of them.
+        struct Reentrant(AtomicUsize);
+
+        impl tracing::Subscriber for Reentrant {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
+            }
+            fn new_span(&self, _: &Attributes<'_>) -> Id {
+                Id::from_u64(1)
+            }
+            fn record(&self, _: &Id, _: &Record<'_>) {}
+            fn record_follows_from(&self, _: &Id, _: &Id) {}
+            fn event(&self, _: &Event<'_>) {
+                self.0.fetch_add(1, Ordering::Relaxed);
+                unsafe { churn(16) };
+            }
+            fn enter(&self, _: &Id) {}
+            fn exit(&self, _: &Id) {}
+        }
+
+        let subscriber = std::sync::Arc::new(Reentrant(AtomicUsize::new(0)));
+        tracing::subscriber::with_default(subscriber.clone(), || unsafe {
+            // One policy decision, then one large wipe; the nested frees emit nothing.
+            churn(16);
+            assert_eq!(subscriber.0.load(Ordering::Relaxed), 1);
+            churn(8192);
+            assert_eq!(subscriber.0.load(Ordering::Relaxed), 3);
+        });
+    }
+
+    #[test]
+    fn test_dealloc_hook() {
+        use core::alloc::{GlobalAlloc, Layout};
+        use std::sync::Mutex;
+
+        /// Records the first byte of the block each time it is called.
+        struct Audit(Mutex<Vec<(&'static str, usize, u8)>>);
+
+        impl super::DeallocHook for Audit {
+            fn before_wipe(&self, ptr: *mut u8, layout: Layout) {
+                self.0.lock().unwrap().push(("before", layout.size(), unsafe { *ptr }));
+            }
+
+            fn after_wipe(&self, ptr: *mut u8, layout: Layout) {
+                self.0.lock().unwrap().push(("after", layout.size(), unsafe { *ptr }));
+            }
+        }
+
+        static AUDIT: Audit = Audit(Mutex::new(Vec::new()));
+
+        let alloc = super::ZeroizingGlobalAllocator::new(std::alloc::System).with_hook(&AUDIT);
+        let layout = Layout::from_size_align(24, 8).unwrap();
+        unsafe {
+            let ptr = alloc.alloc(layout);
+            ptr.write_bytes(0xff, 24);
+            alloc.dealloc(ptr, layout);
+        }
+        assert_eq!(&AUDIT.0.lock().unwrap()[..], &[("before", 24, 0xff), ("after", 24, 0)]);
+
+        // The tail an in-place shrink gives up is reported on its own.
+        #[cfg(all(feature = "std", target_os = "linux"))]
+        unsafe {
+            static TAILS: Audit = Audit(Mutex::new(Vec::new()));
+
+            let alloc =
+                super::ZeroizingGlobalAllocator::with_backend(std::alloc::System).with_hook(&TAILS);
+            let layout = Layout::from_size_align(64, 8).unwrap();
+            let ptr = alloc.alloc(layout);
+            ptr.write_bytes(0xff, 64);
+            assert_eq!(alloc.realloc(ptr, layout, 48), ptr);
+            assert_eq!(&TAILS.0.lock().unwrap()[..], &[("before", 16, 0xff), ("after", 16, 0)]);
+            alloc.dealloc(ptr, Layout::from_size_align(48, 8).unwrap());
+        }
+    }
+
+    /// A page shared with forked children, which zero it when wiped.
+    #[cfg(all(feature = "std", unix))]
+    struct SharedPage(core::sync::atomic::AtomicUsize);
+
+    #[cfg(all(feature = "std", unix))]
+    impl SharedPage {
+        const LEN: usize = 64;
+
+        /// Maps the page and fills it with `0xff`.
+        unsafe fn map(&self) -> *mut u8 {
+            let page = libc::mmap(
+                core::ptr::null_mut(),
+                Self::LEN,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED | libc::MAP_ANONYMOUS,
+                -1,
+                0,
+            );
+            assert_ne!(page, libc::MAP_FAILED);
+            let page = page as *mut u8;
+            page.write_bytes(0xff, Self::LEN);
+            self.0.store(page as usize, core::sync::atomic::Ordering::Relaxed);
+            page
+        }
+    }
+
+    #[cfg(all(feature = "std", unix))]
+    impl super::EmergencyWipe for SharedPage {
+        fn emergency_wipe(&self) {
+            let page = self.0.load(core::sync::atomic::Ordering::Relaxed) as *mut u8;
+            unsafe { page.write_bytes(0, Self::LEN) };
+        }
+    }
+
+    /// Reports on stdout that it was wiped.
+    #[cfg(all(feature = "std", unix))]
+    struct ExitMarker;
+
+    #[cfg(all(feature = "std", unix))]
+    impl ExitMarker {
+        const LINE: &'static [u8] = b"zeroize_alloc: wiped at exit\n";
+    }
+
+    #[cfg(all(feature = "std", unix))]
+    impl super::EmergencyWipe for ExitMarker {
+        fn emergency_wipe(&self) {
+            unsafe { libc::write(1, Self::LINE.as_ptr() as *const _, Self::LINE.len()) };
+        }
+    }
+
+    #[test]
+    #[cfg(all(feature = "std", unix))]
+    fn test_exit_wipe() {
+        use core::alloc::{GlobalAlloc, Layout};
+
+        const CHILD: &str = "ZEROIZE_ALLOC_EXIT_WIPE_CHILD";
+
+        // Run again as a fresh process below, which wipes on its way out.
+        if std::env::var_os(CHILD).is_some() {
+            super::register_emergency_wipe(&ExitMarker);
+            super::install_exit_wipe().unwrap();
+            return;
+        }
+
+        static REGISTRY: super::Registry = super::Registry::new();
+
+        let alloc =
+            super::ZeroizingGlobalAllocator::new(std::alloc::System).with_registry(&REGISTRY);
+        let layout = Layout::from_size_align(32, 8).unwrap();
+        unsafe {
+            assert!(super::register_emergency_wipe(&REGISTRY));
+            let ptr = alloc.alloc(layout);
+            ptr.write_bytes(0xff, 32);
+            super::emergency_wipe_all();
+            assert!(core::slice::from_raw_parts(ptr, 32).iter().all(|&b| b == 0));
+            alloc.dealloc(ptr, layout);
+        }
+
+        // Forking and calling `exit` could deadlock on locks other test threads held, so the
+        // test binary is run again with only this test.
+        let output = std::process::Command::new(std::env::current_exe().unwrap())
+            .args(["--exact", "test::test_exit_wipe", "--test-threads=1"])
+            .env(CHILD, "1")
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert!(output.stdout.ends_with(ExitMarker::LINE));
+    }
+
+    #[test]
+    #[cfg(all(feature = "std", unix))]
+    fn test_crash_wipe() {
+        static PAGE: SharedPage = SharedPage(core::sync::atomic::AtomicUsize::new(0));
+
+        unsafe {
+            // The child wipes the shared page before dying of the signal.
+            let page = PAGE.map();
+            let pid = libc::fork();
+            if pid == 0 {
+                let no_core = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+                libc::setrlimit(libc::RLIMIT_CORE, &no_core);
+                super::register_emergency_wipe(&PAGE);
+                if super::install_crash_wipe().is_err() {
+                    libc::_exit(1);
+                }
+                libc::raise(libc::SIGSEGV);
+                libc::_exit(0);
+            }
+            let mut status = 0;
+            assert_eq!(libc::waitpid(pid, &mut status, 0), pid);
+            assert!(libc::WIFSIGNALED(status));
+            assert_eq!(libc::WTERMSIG(status), libc::SIGSEGV);
+            assert!(core::slice::from_raw_parts(page, SharedPage::LEN).iter().all(|&b| b == 0));
+            libc::munmap(page as *mut libc::c_void, SharedPage::LEN);
+        }
+    }
+
+    #[test]
+    #[cfg(all(feature = "std", unix))]
+    fn test_harden_process() {
+        unsafe {
+            // Hardening is irreversible, so it happens in a child.
+            let pid = libc::fork();
+            if pid == 0 {
+                let report = super::harden_process(false);
+                let mut limit = libc::rlimit { rlim_cur: 1, rlim_max: 1 };
+                libc::getrlimit(libc::RLIMIT_CORE, &mut limit);
+                let ok = report.is_complete()
+                    && report.core_limit == Some(Ok(()))
+                    && report.lock_all.is_none()
+                    && limit.rlim_cur == 0;
+                #[cfg(target_os = "linux")]
+                let ok = ok && libc::prctl(libc::PR_GET_DUMPABLE) == 0;
+                libc::_exit(if ok { 0 } else { 1 });
+            }
+            let mut status = 0;
+            assert_eq!(libc::waitpid(pid, &mut status, 0), pid);
+            assert!(libc::WIFEXITED(status));
+            assert_eq!(libc::WEXITSTATUS(status), 0);
+        }
+    }
+
+    /// Hands out `System` memory but never frees it, so tests can inspect released blocks.
+    #[cfg(feature = "std")]
+    struct Leaky;
+
+    #[cfg(feature = "std")]
+    unsafe impl core::alloc::Allocator for Leaky {
+        fn allocate(
+            &self,
+            layout: core::alloc::Layout,
+        ) -> Result<core::ptr::NonNull<[u8]>, core::alloc::AllocError> {
+            core::alloc::Allocator::allocate(&std::alloc::System, layout)
+        }
+
+        unsafe fn deallocate(&self, _ptr: core::ptr::NonNull<u8>, _layout: core::alloc::Layout) {}
+    }
+
+    #[cfg(feature = "std")]
+    unsafe impl super::Backend for Leaky {
+        unsafe fn resize_in_place(
+            &self,
+            ptr: *mut u8,
+            layout: core::alloc::Layout,
+            new_size: usize,
+        ) -> bool {
+            std::alloc::System.resize_in_place(ptr, layout, new_size)
+        }
+
+        unsafe fn usable_size(&self, ptr: *mut u8, layout: core::alloc::Layout) -> usize {
+            std::alloc::System.usable_size(ptr, layout)
+        }
+    }
+
+    #[test]
+    #[cfg(all(feature = "std", target_os = "linux"))]
+    fn test_allocator_resize() {
+        use core::alloc::{Allocator, Layout};
+
+        let alloc = super::ZeroizingAllocator::with_backend(Leaky);
+        let big = Layout::from_size_align(64, 8).unwrap();
+        let small = Layout::from_size_align(48, 8).unwrap();
+        let tiny = Layout::from_size_align(16, 8).unwrap();
+        let huge = Layout::from_size_align(1 << 20, 8).unwrap();
+        unsafe {
+            let ptr = alloc.allocate(big).unwrap().cast::<u8>();
+            ptr.as_ptr().write_bytes(0xff, big.size());
+            let shrunk = alloc.shrink(ptr, big, small).unwrap().cast::<u8>();
+            assert_eq!(shrunk, ptr);
+            assert!(core::slice::from_raw_parts(ptr.as_ptr().add(48), 16).iter().all(|&b| b == 0));
+
+            let regrown = alloc.grow_zeroed(shrunk, small, big).unwrap().cast::<u8>();
+            assert_eq!(regrown, ptr);
+            let bytes = core::slice::from_raw_parts(ptr.as_ptr(), big.size());
+            assert!(bytes[..48].iter().all(|&b| b == 0xff));
+            assert!(bytes[48..].iter().all(|&b| b == 0));
+
+            let moved = alloc.grow(regrown, big, huge).unwrap().cast::<u8>();
+            assert_ne!(moved, ptr);
+            assert!(core::slice::from_raw_parts(moved.as_ptr(), 48).iter().all(|&b| b == 0xff));
+            assert!(core::slice::from_raw_parts(ptr.as_ptr(), big.size()).iter().all(|&b| b == 0));
+
+            // Shrinking far below the size class moves as well.
+            let trimmed = alloc.shrink(moved, huge, tiny).unwrap().cast::<u8>();
+            assert_ne!(trimmed, moved);
+            assert!(core::slice::from_raw_parts(trimmed.as_ptr(), 16).iter().all(|&b| b == 0xff));
+            let old = core::slice::from_raw_parts(moved.as_ptr(), huge.size());
+            assert!(old.iter().all(|&b| b == 0));
+
+            // Without the backend the shrink moves, and the old block is wiped as a whole.
+            let alloc = super::ZeroizingAllocator::new(Leaky);
+            let ptr = alloc.allocate(big).unwrap().cast::<u8>();
+            ptr.as_ptr().write_bytes(0xff, big.size());
+            let shrunk = alloc.shrink(ptr, big, small).unwrap().cast::<u8>();
+            assert_ne!(shrunk, ptr);
+            assert!(core::slice::from_raw_parts(shrunk.as_ptr(), 16).iter().all(|&b| b == 0xff));
+            assert!(core::slice::from_raw_parts(ptr.as_ptr(), big.size()).iter().all(|&b| b == 0));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_usable_size() {
+        use super::Backend;
+        use core::alloc::{GlobalAlloc, Layout};
+
+        let alloc = super::ZeroizingGlobalAllocator::with_backend(std::alloc::System)
+            .wipe_usable_size(true);
+        let layout = Layout::from_size_align(20, 4).unwrap();
+        unsafe {
+            let ptr = alloc.alloc(layout);
+            let usable = std::alloc::System.usable_size(ptr, layout);
+            assert!(usable >= layout.size());
+            ptr.write_bytes(0xff, usable);
+            alloc.dealloc(ptr, layout);
+            assert!(core::slice::from_raw_parts(ptr, usable).iter().all(|&b| b == 0));
+        }
+    }
+
+    /// Frees through `System` and counts how many blocks it was handed back.
+    struct Counting(core::sync::atomic::AtomicUsize);
+
+    unsafe impl core::alloc::GlobalAlloc for Counting {
+        unsafe fn alloc(&self, layout: core::alloc::Layout) -> *mut u8 {
+            std::alloc::System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: core::alloc::Layout) {
+            self.0.fetch_add(1, core::sync::atomic::Ordering::Relaxed);
+            std::alloc::System.dealloc(ptr, layout)
+        }
+    }
+
+    #[test]
+    fn test_quarantine() {
+        use core::alloc::{GlobalAlloc, Layout};
+        use core::sync::atomic::{AtomicUsize, Ordering};
+
+        let quarantine =
+            super::Quarantine::<_, 2>::with_capacity(Counting(AtomicUsize::new(0)), 100);
+        let released = || quarantine.inner().0.load(Ordering::Relaxed);
+        let small = Layout::from_size_align(40, 8).unwrap();
+        unsafe {
+            let a = quarantine.alloc(small);
+            let b = quarantine.alloc(small);
+            let c = quarantine.alloc(small);
+            quarantine.dealloc(a, small);
+            quarantine.dealloc(b, small);
+            assert_eq!((quarantine.len(), quarantine.bytes(), released()), (2, 80, 0));
+            // Both the count and the byte limit push out the oldest block.
+            quarantine.dealloc(c, small);
+            assert_eq!((quarantine.len(), quarantine.bytes(), released()), (2, 80, 1));
+
+            let large = Layout::from_size_align(200, 8).unwrap();
+            quarantine.dealloc(quarantine.alloc(large), large);
+            assert_eq!((quarantine.len(), released()), (2, 2));
+        }
+        quarantine.flush();
+        assert_eq!((quarantine.len(), released()), (0, 4));
+    }
+
+    #[test]
+    fn test_quarantine_uaf_check() {
+        use core::alloc::{GlobalAlloc, Layout};
+        use std::sync::Mutex;
+
+        static REPORTS: Mutex<Vec<(usize, super::UafReport)>> = Mutex::new(Vec::new());
+
+        fn record(ptr: *mut u8, _layout: Layout, report: &super::UafReport) {
+            REPORTS.lock().unwrap().push((ptr as usize, *report));
+        }
+
+        let quarantine = super::Quarantine::<_, 1>::with_capacity(std::alloc::System, 1024)
+            .check_on_evict(0, record);
+        let layout = Layout::from_size_align(32, 8).unwrap();
+        unsafe {
+            let a = quarantine.alloc_zeroed(layout);
+            let b = quarantine.alloc_zeroed(layout);
+            let c = quarantine.alloc_zeroed(layout);
+            quarantine.dealloc(a, layout);
+            quarantine.dealloc(b, layout);
+            assert!(REPORTS.lock().unwrap().is_empty());
+            b.add(5).write(1);
+            b.add(9).write(2);
+            quarantine.dealloc(c, layout);
+            let report = super::UafReport { expected: 0, first_offset: 5, modified: 2 };
+            assert_eq!(&REPORTS.lock().unwrap()[..], &[(b as usize, report)]);
+        }
+    }
+
+    #[test]
+    fn test_junk_fill() {
+        use core::alloc::{GlobalAlloc, Layout};
+
+        let alloc = super::ZeroizingGlobalAllocator::new(std::alloc::System).junk_fill(0xa5);
+        let layout = Layout::from_size_align(24, 8).unwrap();
+        unsafe {
+            let ptr = alloc.alloc(layout);
+            assert!(core::slice::from_raw_parts(ptr, 24).iter().all(|&b| b == 0xa5));
+            ptr.write_bytes(1, 24);
+            let grown = alloc.realloc(ptr, layout, 4096);
+            let bytes = core::slice::from_raw_parts(grown, 4096);
+            assert!(bytes[..24].iter().all(|&b| b == 1));
+            assert!(bytes[24..].iter().all(|&b| b == 0xa5));
+
+            let zeroed = alloc.alloc_zeroed(layout);
+            assert!(core::slice::from_raw_parts(zeroed, 24).iter().all(|&b| b == 0));
+        }
+    }
+
     quickcheck::quickcheck! {
         fn prop(v1: Vec<u8>, v2: Vec<u8>) -> bool {
             let mut v1 = v1;
-            if v1.len() == 0 || v2.len() == 0 {
+            if v1.is_empty() || v2.is_empty() {
                 return true;
             }
             let ptr1: *const u8 = &v1[0];
@@ -89,6 +1469,8 @@ mod test {
             let ptr2: *const u8 = &v2[0];
             v1.extend(v2);
             let ptr3: *const u8 = &v1[0];
+            // `v1` is full after `shrink_to_fit`, and `ALLOC` never grows blocks in place.
+            assert_ne!(ptr1, ptr3);
             assert_eq!(unsafe { ptr1.as_ref() }, Some(&0));
             assert_eq!(unsafe { ptr2.as_ref() }, Some(&0));
             drop(v1);
@@ -0,0 +1,51 @@
+use core::alloc::Layout;
+
+/// Decides, per block, whether a wrapper wipes it.
+///
+/// Wiping every small node can cost more than the coverage is worth when secrets only live in a
+/// few large buffers. Implemented for the provided predicates and for any `Fn(Layout) -> bool`.
+pub trait WipePolicy: Sync {
+    /// Whether a block allocated with `layout` is wiped when it is freed or shrunk.
+    fn should_wipe(&self, layout: Layout) -> bool;
+}
+
+impl<F: Fn(Layout) -> bool + Sync> WipePolicy for F {
+    fn should_wipe(&self, layout: Layout) -> bool {
+        self(layout)
+    }
+}
+
+/// Wipes blocks whose size lies in `min..=max`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SizeRange {
+    pub min: usize,
+    pub max: usize,
+}
+
+impl SizeRange {
+    /// Wipes blocks of at least `min` bytes.
+    pub const fn at_least(min: usize) -> Self {
+        Self { min, max: usize::MAX }
+    }
+
+    /// Wipes blocks of at most `max` bytes.
+    pub const fn at_most(max: usize) -> Self {
+        Self { min: 0, max }
+    }
+}
+
+impl WipePolicy for SizeRange {
+    fn should_wipe(&self, layout: Layout) -> bool {
+        (self.min..=self.max).contains(&layout.size())
+    }
+}
+
+/// Wipes blocks aligned to at least the given power of two, e.g. page-aligned key buffers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MinAlign(pub usize);
+
+impl WipePolicy for MinAlign {
+    fn should_wipe(&self, layout: Layout) -> bool {
+        layout.align() >= self.0
+    }
+}
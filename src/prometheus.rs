@@ -0,0 +1,67 @@
+use core::fmt::{self, Write};
+
+use crate::{Histogram, QuarantineDepth, StatsSnapshot};
+
+/// Wipe latency buckets exported, the last one ending at about 17 seconds.
+const LATENCY_BUCKETS: usize = 35;
+
+/// Renders `stats`, and the depth of a quarantine if given, in the Prometheus text exposition
+/// format. All metric names start with `zeroize_alloc_`.
+///
+/// Wipe latencies are exported as the `zeroize_alloc_wipe_duration_seconds` histogram, with
+/// power-of-two buckets from 1 nanosecond to about 17 seconds.
+pub fn write_prometheus(
+    out: &mut impl Write,
+    stats: &StatsSnapshot,
+    quarantine: Option<QuarantineDepth>,
+) -> fmt::Result {
+    let counters = [
+        ("allocations_total", "Blocks allocated.", stats.allocations),
+        ("deallocations_total", "Blocks deallocated.", stats.deallocations),
+        ("wiped_bytes_total", "Bytes overwritten by the wipe strategy.", stats.bytes_wiped),
+    ];
+    for (name, help, value) in counters {
+        metric(out, name, "counter", help, value)?;
+    }
+    let gauges = [
+        ("live_bytes", "Bytes currently allocated.", stats.live_bytes),
+        ("peak_live_bytes", "Highest number of bytes allocated at once.", stats.peak_live_bytes),
+    ];
+    for (name, help, value) in gauges {
+        metric(out, name, "gauge", help, value)?;
+    }
+    latency(out, &stats.wipe_latency, stats.wipe_nanos)?;
+    if let Some(depth) = quarantine {
+        metric(out, "quarantine_blocks", "gauge", "Blocks held in quarantine.", depth.blocks)?;
+        metric(out, "quarantine_bytes", "gauge", "Bytes held in quarantine.", depth.bytes)?;
+    }
+    Ok(())
+}
+
+fn metric(
+    out: &mut impl Write,
+    name: &str,
+    kind: &str,
+    help: &str,
+    value: impl fmt::Display,
+) -> fmt::Result {
+    writeln!(out, "# HELP zeroize_alloc_{name} {help}")?;
+    writeln!(out, "# TYPE zeroize_alloc_{name} {kind}")?;
+    writeln!(out, "zeroize_alloc_{name} {value}")
+}
+
+fn latency(out: &mut impl Write, histogram: &Histogram, total_nanos: u64) -> fmt::Result {
+    const NAME: &str = "zeroize_alloc_wipe_duration_seconds";
+    writeln!(out, "# HELP {NAME} Time spent wiping a block.")?;
+    writeln!(out, "# TYPE {NAME} histogram")?;
+    // Wipes measured at 0 ns are counted from the first exported bucket on.
+    let mut cumulative = histogram.buckets[0];
+    for bucket in 1..LATENCY_BUCKETS {
+        cumulative += histogram.buckets[bucket];
+        let le = Histogram::upper_bound(bucket) as f64 / 1e9;
+        writeln!(out, "{NAME}_bucket{{le=\"{le}\"}} {cumulative}")?;
+    }
+    writeln!(out, "{NAME}_bucket{{le=\"+Inf\"}} {}", histogram.total())?;
+    writeln!(out, "{NAME}_sum {}", total_nanos as f64 / 1e9)?;
+    writeln!(out, "{NAME}_count {}", histogram.total())
+}
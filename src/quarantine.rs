@@ -0,0 +1,233 @@
+use core::alloc::{GlobalAlloc, Layout};
+
+use crate::spin::SpinLock;
+use crate::trace::event;
+use crate::Backend;
+
+/// Holds freed blocks back from the inner allocator for a while.
+///
+/// Use it as the inner allocator of a [`ZeroizingGlobalAllocator`](crate::ZeroizingGlobalAllocator):
+/// blocks are wiped first and then parked here in FIFO order, so reads through dangling
+/// pointers keep seeing zeros until the block is evicted. At most `N` blocks and `max_bytes`
+/// bytes are held; the oldest blocks are handed to the inner allocator once either limit would
+/// be exceeded. Blocks larger than `max_bytes` bypass the quarantine.
+///
+/// With [`check_on_evict`](Self::check_on_evict), evicted blocks are verified to still hold the
+/// wipe pattern, turning writes through dangling pointers into reports.
+pub struct Quarantine<Alloc: GlobalAlloc, const N: usize = 64> {
+    alloc: Alloc,
+    max_bytes: usize,
+    check: Option<(u8, UafHandler)>,
+    ring: SpinLock<Ring<N>>,
+}
+
+/// Called with an evicted block, its layout and what was found in it when the block was
+/// written to while in quarantine.
+pub type UafHandler = fn(ptr: *mut u8, layout: Layout, report: &UafReport);
+
+/// Writes found in a quarantined block on eviction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UafReport {
+    /// The byte every position of the block was expected to hold.
+    pub expected: u8,
+    /// Offset of the first byte that differed.
+    pub first_offset: usize,
+    /// Number of bytes that differed.
+    pub modified: usize,
+}
+
+/// How much a [`Quarantine`] currently holds.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct QuarantineDepth {
+    /// Number of blocks held.
+    pub blocks: usize,
+    /// Total size of the blocks held.
+    pub bytes: usize,
+}
+
+#[derive(Clone, Copy)]
+struct Block {
+    ptr: *mut u8,
+    layout: Layout,
+}
+
+struct Ring<const N: usize> {
+    blocks: [Option<Block>; N],
+    head: usize,
+    len: usize,
+    bytes: usize,
+}
+
+unsafe impl<const N: usize> Send for Ring<N> {}
+
+impl<const N: usize> Ring<N> {
+    fn push(&mut self, block: Block) {
+        self.blocks[(self.head + self.len) % N] = Some(block);
+        self.len += 1;
+        self.bytes += block.layout.size();
+    }
+
+    fn pop(&mut self) -> Option<Block> {
+        let block = self.blocks[self.head].take()?;
+        self.head = (self.head + 1) % N;
+        self.len -= 1;
+        self.bytes -= block.layout.size();
+        Some(block)
+    }
+}
+
+impl<A: GlobalAlloc> Quarantine<A> {
+    /// Wraps `alloc`, holding at most 64 blocks and `max_bytes` bytes.
+    pub const fn new(alloc: A, max_bytes: usize) -> Self {
+        Self::with_capacity(alloc, max_bytes)
+    }
+}
+
+impl<A: GlobalAlloc, const N: usize> Quarantine<A, N> {
+    /// Wraps `alloc`, holding at most `N` blocks and `max_bytes` bytes.
+    pub const fn with_capacity(alloc: A, max_bytes: usize) -> Self {
+        assert!(N > 0, "quarantine capacity must be non-zero");
+        Self {
+            alloc,
+            max_bytes,
+            check: None,
+            ring: SpinLock::new(Ring {
+                blocks: [None; N],
+                head: 0,
+                len: 0,
+                bytes: 0,
+            }),
+        }
+    }
+
+    /// Verifies that evicted blocks still consist of `expected` bytes only (the last byte the
+    /// wipe strategy writes), calling `handler` for blocks that were written to after being
+    /// freed.
+    ///
+    /// The quarantine cannot tell whether the wrapper wiped a block, so every block it holds
+    /// must have been wiped to `expected`. Blocks the wrapper skips are reported as if they had
+    /// been written to: those a [`WipePolicy`](crate::WipePolicy) does not select, all blocks
+    /// while wiping is turned off with [`set_enabled`](crate::set_enabled) or the wrapper's own
+    /// switch, and every block when the wipe strategy does not end on a fixed byte, such as a
+    /// [`MultiPass`](crate::MultiPass) whose last pass is [`Pass::Random`](crate::Pass::Random).
+    /// Only enable the check on wrappers that wipe every block.
+    pub const fn check_on_evict(mut self, expected: u8, handler: UafHandler) -> Self {
+        self.check = Some((expected, handler));
+        self
+    }
+
+    /// The wrapped allocator.
+    pub const fn inner(&self) -> &A {
+        &self.alloc
+    }
+
+    /// Number of blocks currently held.
+    pub fn len(&self) -> usize {
+        self.ring.with(|ring| ring.len)
+    }
+
+    /// Whether no block is currently held.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Total size of the blocks currently held.
+    pub fn bytes(&self) -> usize {
+        self.ring.with(|ring| ring.bytes)
+    }
+
+    /// Number and total size of the blocks currently held, read at the same time.
+    pub fn depth(&self) -> QuarantineDepth {
+        self.ring.with(|ring| QuarantineDepth {
+            blocks: ring.len,
+            bytes: ring.bytes,
+        })
+    }
+
+    /// Hands every held block to the inner allocator.
+    pub fn flush(&self) {
+        while let Some(block) = self.ring.with(Ring::pop) {
+            unsafe { self.release(block) };
+        }
+    }
+
+    #[cfg_attr(feature = "aggressive-inline", inline(always))]
+    unsafe fn release(&self, block: Block) {
+        event!(
+            tracing::Level::TRACE,
+            ptr = ?block.ptr,
+            size = block.layout.size(),
+            "quarantine eviction"
+        );
+        if let Some((expected, handler)) = self.check {
+            let bytes = core::slice::from_raw_parts(block.ptr, block.layout.size());
+            if let Some(first_offset) = bytes.iter().position(|&b| b != expected) {
+                let report = UafReport {
+                    expected,
+                    first_offset,
+                    modified: bytes[first_offset..].iter().filter(|&&b| b != expected).count(),
+                };
+                event!(
+                    tracing::Level::WARN,
+                    ptr = ?block.ptr,
+                    size = block.layout.size(),
+                    first_offset,
+                    modified = report.modified,
+                    "quarantined block written to"
+                );
+                handler(block.ptr, block.layout, &report);
+            }
+        }
+        self.alloc.dealloc(block.ptr, block.layout);
+    }
+}
+
+impl<A: GlobalAlloc, const N: usize> Drop for Quarantine<A, N> {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+unsafe impl<A: GlobalAlloc, const N: usize> GlobalAlloc for Quarantine<A, N> {
+    #[cfg_attr(feature = "aggressive-inline", inline(always))]
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        self.alloc.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        if layout.size() > self.max_bytes {
+            return self.alloc.dealloc(ptr, layout);
+        }
+        let mut incoming = Some(Block { ptr, layout });
+        // Evicted blocks are released outside the lock, one at a time.
+        while let Some(block) = self.ring.with(|ring| {
+            if let Some(block) = incoming {
+                if ring.len < N && ring.bytes + block.layout.size() <= self.max_bytes {
+                    ring.push(block);
+                    incoming = None;
+                    return None;
+                }
+            }
+            ring.pop()
+        }) {
+            self.release(block);
+        }
+    }
+
+    #[cfg_attr(feature = "aggressive-inline", inline(always))]
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        self.alloc.alloc_zeroed(layout)
+    }
+}
+
+unsafe impl<A: GlobalAlloc + Backend, const N: usize> Backend for Quarantine<A, N> {
+    #[cfg_attr(feature = "aggressive-inline", inline(always))]
+    unsafe fn resize_in_place(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> bool {
+        self.alloc.resize_in_place(ptr, layout, new_size)
+    }
+
+    #[cfg_attr(feature = "aggressive-inline", inline(always))]
+    unsafe fn usable_size(&self, ptr: *mut u8, layout: Layout) -> usize {
+        self.alloc.usable_size(ptr, layout)
+    }
+}
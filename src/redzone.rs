@@ -0,0 +1,158 @@
+use core::alloc::{AllocError, Allocator, GlobalAlloc, Layout};
+use core::ptr::NonNull;
+
+use crate::trace::event;
+
+/// Pads every allocation with `SIZE` canary bytes before and after the user region and checks
+/// them when the block is freed.
+///
+/// Put it *around* a zeroizing wrapper, e.g. `Redzone<ZeroizingGlobalAllocator<System>>`, so the
+/// canaries are verified before the block is wiped. Damaged canaries are passed to the handler,
+/// after which the block is freed as usual.
+pub struct Redzone<Alloc, const SIZE: usize = 16> {
+    alloc: Alloc,
+    canary: u8,
+    handler: RedzoneHandler,
+}
+
+/// Called with a freed block, its layout and the canary bytes found damaged.
+pub type RedzoneHandler = fn(ptr: *mut u8, layout: Layout, report: &RedzoneReport<'_>);
+
+/// Canary bytes found damaged around a freed block.
+#[derive(Debug)]
+pub struct RedzoneReport<'a> {
+    /// Offsets of damaged bytes relative to the start of the block, negative before it and at
+    /// least `layout.size()` after it. Holds the first [`Self::MAX_DAMAGED`] only.
+    pub damaged: &'a [isize],
+    /// Total number of damaged bytes.
+    pub total: usize,
+}
+
+impl RedzoneReport<'_> {
+    /// How many damaged offsets a report lists at most.
+    pub const MAX_DAMAGED: usize = 64;
+}
+
+impl<A> Redzone<A> {
+    /// Wraps `alloc` with 16-byte redzones filled with `0xca`.
+    pub const fn new(alloc: A, handler: RedzoneHandler) -> Self {
+        Self::with_size(alloc, handler)
+    }
+}
+
+impl<A, const SIZE: usize> Redzone<A, SIZE> {
+    /// Wraps `alloc` with `SIZE`-byte redzones filled with `0xca`.
+    pub const fn with_size(alloc: A, handler: RedzoneHandler) -> Self {
+        Self {
+            alloc,
+            canary: 0xca,
+            handler,
+        }
+    }
+
+    /// Fills redzones with `canary` instead of `0xca`.
+    pub const fn canary(mut self, canary: u8) -> Self {
+        self.canary = canary;
+        self
+    }
+
+    /// The wrapped allocator.
+    pub const fn inner(&self) -> &A {
+        &self.alloc
+    }
+
+    /// The padded layout the inner allocator sees and the offset of the user region in it.
+    fn padded(layout: Layout) -> Option<(Layout, usize)> {
+        let front = SIZE.div_ceil(layout.align()) * layout.align();
+        let size = front.checked_add(layout.size())?.checked_add(SIZE)?;
+        Some((Layout::from_size_align(size, layout.align()).ok()?, front))
+    }
+
+    /// Fills the redzones of a fresh padded block and returns the user region.
+    unsafe fn arm(&self, base: *mut u8, layout: Layout, front: usize) -> *mut u8 {
+        base.write_bytes(self.canary, front);
+        base.add(front + layout.size()).write_bytes(self.canary, SIZE);
+        base.add(front)
+    }
+
+    /// Checks the redzones around `ptr` and returns the start of the padded block.
+    unsafe fn check(&self, ptr: *mut u8, layout: Layout, front: usize) -> *mut u8 {
+        let base = ptr.sub(front);
+        let mut damaged = [0isize; RedzoneReport::MAX_DAMAGED];
+        let mut total = 0;
+        let zones = [
+            (base, front, -(front as isize)),
+            (ptr.add(layout.size()), SIZE, layout.size() as isize),
+        ];
+        for (zone, len, first_offset) in zones {
+            for i in 0..len {
+                if *zone.add(i) != self.canary {
+                    if total < RedzoneReport::MAX_DAMAGED {
+                        damaged[total] = first_offset + i as isize;
+                    }
+                    total += 1;
+                }
+            }
+        }
+        if total != 0 {
+            let report = RedzoneReport {
+                damaged: &damaged[..total.min(RedzoneReport::MAX_DAMAGED)],
+                total,
+            };
+            event!(
+                tracing::Level::WARN,
+                ptr = ?ptr,
+                size = layout.size(),
+                total,
+                "redzone damaged"
+            );
+            (self.handler)(ptr, layout, &report);
+        }
+        base
+    }
+}
+
+unsafe impl<A: GlobalAlloc, const SIZE: usize> GlobalAlloc for Redzone<A, SIZE> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let Some((padded, front)) = Self::padded(layout) else {
+            return core::ptr::null_mut();
+        };
+        let base = self.alloc.alloc(padded);
+        if base.is_null() {
+            return base;
+        }
+        self.arm(base, layout, front)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        let (padded, front) = Self::padded(layout).unwrap_unchecked();
+        let base = self.check(ptr, layout, front);
+        self.alloc.dealloc(base, padded);
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let Some((padded, front)) = Self::padded(layout) else {
+            return core::ptr::null_mut();
+        };
+        let base = self.alloc.alloc_zeroed(padded);
+        if base.is_null() {
+            return base;
+        }
+        self.arm(base, layout, front)
+    }
+}
+
+unsafe impl<A: Allocator, const SIZE: usize> Allocator for Redzone<A, SIZE> {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let (padded, front) = Self::padded(layout).ok_or(AllocError)?;
+        let base = self.alloc.allocate(padded)?.cast::<u8>();
+        let ptr = unsafe { self.arm(base.as_ptr(), layout, front) };
+        Ok(NonNull::slice_from_raw_parts(unsafe { NonNull::new_unchecked(ptr) }, layout.size()))
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        let (padded, front) = Self::padded(layout).unwrap_unchecked();
+        let base = self.check(ptr.as_ptr(), layout, front);
+        self.alloc.deallocate(NonNull::new_unchecked(base), padded);
+    }
+}
@@ -0,0 +1,272 @@
+use core::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+
+use crate::{WideVolatile, WipeStrategy};
+
+const SLOTS: usize = 16384;
+
+/// How many tables can be added once the built-in one fills up; table `i` of them has
+/// `SLOTS << (i + 1)` slots.
+const MAX_GROWN: usize = 20;
+
+/// How far from its home slot a block may be stored, which bounds the cost of every lookup no
+/// matter how many freed slots a table has accumulated.
+const MAX_PROBE: usize = 128;
+
+/// Marks a slot whose block was freed; unlike an empty slot it does not end a lookup.
+const FREED: usize = 1;
+
+/// Keeps track of the live blocks of the wrappers it is attached to, so they can all be wiped at
+/// once with [`emergency_wipe_all`](Self::emergency_wipe_all), e.g. from a signal handler or a
+/// tamper-detection path.
+///
+/// The registry is lock-free. It starts with a built-in table of 16384 slots and, on Unix with
+/// the `std` feature, adds twice as large tables mapped with `mmap` whenever the existing ones
+/// have no room for a block, so it can track every allocation of the global allocator. A
+/// block that still cannot be tracked (without `std`, or when `mmap` fails) would be missed by
+/// an emergency wipe, so the registry calls its [`RegistryFullHandler`], which aborts by
+/// default.
+pub struct Registry {
+    slots: [Slot; SLOTS],
+    grown: [AtomicPtr<Slot>; MAX_GROWN],
+    live: AtomicUsize,
+    untracked: AtomicUsize,
+    handler: RegistryFullHandler,
+}
+
+/// Called with a block the registry had no room for. If it returns, the block is allocated but
+/// not tracked, and counted by [`Registry::untracked`].
+pub type RegistryFullHandler = fn(ptr: *mut u8, size: usize);
+
+struct Slot {
+    ptr: AtomicUsize,
+    /// Zero while the slot is being filled or emptied.
+    size: AtomicUsize,
+}
+
+impl Registry {
+    /// An empty registry that prints a diagnostic and aborts the process when it cannot grow.
+    #[cfg(feature = "std")]
+    pub const fn new() -> Self {
+        Self::with_handler(abort_when_full)
+    }
+
+    /// An empty registry that calls `handler` when it cannot grow.
+    pub const fn with_handler(handler: RegistryFullHandler) -> Self {
+        Self {
+            slots: [const {
+                Slot {
+                    ptr: AtomicUsize::new(0),
+                    size: AtomicUsize::new(0),
+                }
+            }; SLOTS],
+            grown: [const { AtomicPtr::new(core::ptr::null_mut()) }; MAX_GROWN],
+            live: AtomicUsize::new(0),
+            untracked: AtomicUsize::new(0),
+            handler,
+        }
+    }
+
+    /// Number of blocks currently tracked.
+    pub fn len(&self) -> usize {
+        self.live.load(Ordering::Relaxed)
+    }
+
+    /// Whether no block is currently tracked.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of blocks that could not be tracked because the registry could not grow and the
+    /// handler returned.
+    pub fn untracked(&self) -> usize {
+        self.untracked.load(Ordering::Relaxed)
+    }
+
+    /// Zeroes every tracked block, leaving them allocated.
+    ///
+    /// Only atomics and volatile stores are used, so this is async-signal-safe. Threads still
+    /// running may keep writing to their blocks, and a block freed concurrently may be zeroed
+    /// after it was released; stop other threads first if that matters.
+    pub fn emergency_wipe_all(&self) {
+        for table in self.tables() {
+            for slot in table {
+                let ptr = slot.ptr.load(Ordering::Acquire);
+                if ptr <= FREED {
+                    continue;
+                }
+                let size = slot.size.load(Ordering::Acquire);
+                // The slot may have been emptied and refilled with another block in between, in
+                // which case `size` belongs to that block.
+                if size != 0 && slot.ptr.load(Ordering::Acquire) == ptr {
+                    unsafe { WideVolatile.wipe(ptr as *mut u8, size) };
+                }
+            }
+        }
+    }
+
+    /// The built-in table followed by the grown ones, in the order they were added.
+    fn tables(&self) -> impl Iterator<Item = &[Slot]> {
+        let grown = self.grown.iter().enumerate().map_while(|(i, table)| {
+            let table = table.load(Ordering::Acquire);
+            (!table.is_null())
+                .then(|| unsafe { core::slice::from_raw_parts(table, grown_len(i)) })
+        });
+        core::iter::once(&self.slots[..]).chain(grown)
+    }
+
+    /// The slots `ptr` may be stored in within `table`.
+    fn window(table: &[Slot], ptr: usize) -> impl Iterator<Item = &Slot> {
+        let home = ((ptr >> 4).wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 16) % table.len();
+        (0..MAX_PROBE).map(move |i| &table[(home + i) % table.len()])
+    }
+
+    fn find(&self, ptr: usize) -> Option<&Slot> {
+        self.tables().find_map(|table| {
+            for slot in Self::window(table, ptr) {
+                match slot.ptr.load(Ordering::Acquire) {
+                    0 => return None,
+                    found if found == ptr => return Some(slot),
+                    _ => {}
+                }
+            }
+            None
+        })
+    }
+
+    /// Stores `ptr` in the window of `table`, returning whether there was room.
+    fn claim(&self, table: &[Slot], ptr: *mut u8, size: usize) -> bool {
+        for slot in Self::window(table, ptr as usize) {
+            let current = slot.ptr.load(Ordering::Relaxed);
+            if current <= FREED
+                && slot
+                    .ptr
+                    .compare_exchange(current, ptr as usize, Ordering::AcqRel, Ordering::Relaxed)
+                    .is_ok()
+            {
+                slot.size.store(size, Ordering::Release);
+                self.live.fetch_add(1, Ordering::Relaxed);
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Starts tracking a block.
+    pub(crate) fn insert(&self, ptr: *mut u8, size: usize) {
+        if ptr.is_null() || size == 0 {
+            return;
+        }
+        if self.claim(&self.slots, ptr, size) {
+            return;
+        }
+        for i in 0..MAX_GROWN {
+            match self.grow(i) {
+                Some(table) if self.claim(table, ptr, size) => return,
+                Some(_) => {}
+                None => break,
+            }
+        }
+        (self.handler)(ptr, size);
+        self.untracked.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Returns grown table `i`, adding it if no other thread has yet.
+    fn grow(&self, i: usize) -> Option<&[Slot]> {
+        let len = grown_len(i);
+        let mut table = self.grown[i].load(Ordering::Acquire);
+        if table.is_null() {
+            let fresh = map_table(len);
+            if fresh.is_null() {
+                return None;
+            }
+            table = match self.grown[i].compare_exchange(
+                core::ptr::null_mut(),
+                fresh,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => fresh,
+                Err(winner) => {
+                    unsafe { unmap_table(fresh, len) };
+                    winner
+                }
+            };
+        }
+        Some(unsafe { core::slice::from_raw_parts(table, len) })
+    }
+
+    /// Records the new size of a block resized in place.
+    pub(crate) fn resize(&self, ptr: *mut u8, size: usize) {
+        if let Some(slot) = self.find(ptr as usize) {
+            slot.size.store(size, Ordering::Release);
+        }
+    }
+
+    /// Stops tracking a block.
+    pub(crate) fn remove(&self, ptr: *mut u8) {
+        if let Some(slot) = self.find(ptr as usize) {
+            slot.size.store(0, Ordering::Release);
+            slot.ptr.store(FREED, Ordering::Release);
+            self.live.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Drop for Registry {
+    fn drop(&mut self) {
+        for (i, table) in self.grown.iter().enumerate() {
+            let table = table.load(Ordering::Acquire);
+            if !table.is_null() {
+                unsafe { unmap_table(table, grown_len(i)) };
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Default for Registry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+const fn grown_len(i: usize) -> usize {
+    SLOTS << (i + 1)
+}
+
+/// Maps `len` zeroed, and therefore empty, slots without going through any allocator.
+#[cfg(all(feature = "std", unix))]
+fn map_table(len: usize) -> *mut Slot {
+    let map = unsafe {
+        libc::mmap(
+            core::ptr::null_mut(),
+            len * core::mem::size_of::<Slot>(),
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+            -1,
+            0,
+        )
+    };
+    if map == libc::MAP_FAILED {
+        return core::ptr::null_mut();
+    }
+    map as *mut Slot
+}
+
+#[cfg(all(feature = "std", unix))]
+unsafe fn unmap_table(table: *mut Slot, len: usize) {
+    libc::munmap(table as *mut libc::c_void, len * core::mem::size_of::<Slot>());
+}
+
+#[cfg(not(all(feature = "std", unix)))]
+fn map_table(_len: usize) -> *mut Slot {
+    core::ptr::null_mut()
+}
+
+#[cfg(not(all(feature = "std", unix)))]
+unsafe fn unmap_table(_table: *mut Slot, _len: usize) {}
+
+#[cfg(feature = "std")]
+fn abort_when_full(ptr: *mut u8, size: usize) {
+    crate::fatal::abort(format_args!("registry cannot grow to track {ptr:p} (size {size})"))
+}
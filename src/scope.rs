@@ -0,0 +1,115 @@
+use core::alloc::{GlobalAlloc, Layout};
+use core::cell::Cell;
+use core::marker::PhantomData;
+
+use crate::OwnsAddress;
+
+std::thread_local! {
+    static DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Runs `f` in a sensitive scope, see [`SensitiveGuard`].
+pub fn sensitive_scope<R>(f: impl FnOnce() -> R) -> R {
+    let _guard = SensitiveGuard::enter();
+    f()
+}
+
+/// Whether the current thread is inside a sensitive scope.
+pub fn in_sensitive_scope() -> bool {
+    DEPTH.with(|depth| depth.get() != 0)
+}
+
+/// Marks the current thread as handling secrets until dropped.
+///
+/// While a guard is alive, a [`SensitiveAllocator`] serves the thread's allocations from its
+/// secure heap. Guards nest.
+#[must_use = "the scope ends when the guard is dropped"]
+pub struct SensitiveGuard {
+    // The depth counter belongs to the thread that entered the scope.
+    _thread: PhantomData<*const ()>,
+}
+
+impl SensitiveGuard {
+    /// Enters a sensitive scope on the current thread.
+    pub fn enter() -> Self {
+        DEPTH.with(|depth| depth.set(depth.get() + 1));
+        Self { _thread: PhantomData }
+    }
+}
+
+impl Drop for SensitiveGuard {
+    fn drop(&mut self) {
+        DEPTH.with(|depth| depth.set(depth.get() - 1));
+    }
+}
+
+/// Serves allocations made inside a [`sensitive_scope`] from `secure`, e.g. a locked and
+/// zeroizing arena, and all others from the plain `alloc`.
+///
+/// Deallocations are routed by address, not by the current scope, so blocks may be freed
+/// anywhere. Blocks also stay in the heap they were allocated from when they are resized.
+/// When `secure` is exhausted, allocations inside a scope fail rather than falling back to
+/// `alloc`.
+pub struct SensitiveAllocator<Alloc: GlobalAlloc, Secure: GlobalAlloc + OwnsAddress> {
+    alloc: Alloc,
+    secure: Secure,
+}
+
+impl<A: GlobalAlloc, S: GlobalAlloc + OwnsAddress> SensitiveAllocator<A, S> {
+    /// Serves sensitive allocations from `secure` and the rest from `alloc`.
+    pub const fn new(alloc: A, secure: S) -> Self {
+        Self { alloc, secure }
+    }
+
+    /// The allocator used outside sensitive scopes.
+    pub const fn inner(&self) -> &A {
+        &self.alloc
+    }
+
+    /// The allocator used inside sensitive scopes.
+    pub const fn secure(&self) -> &S {
+        &self.secure
+    }
+}
+
+unsafe impl<A, S> GlobalAlloc for SensitiveAllocator<A, S>
+where
+    A: GlobalAlloc,
+    S: GlobalAlloc + OwnsAddress,
+{
+    #[cfg_attr(feature = "aggressive-inline", inline(always))]
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if in_sensitive_scope() {
+            self.secure.alloc(layout)
+        } else {
+            self.alloc.alloc(layout)
+        }
+    }
+
+    #[cfg_attr(feature = "aggressive-inline", inline(always))]
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        if self.secure.owns(ptr) {
+            self.secure.dealloc(ptr, layout)
+        } else {
+            self.alloc.dealloc(ptr, layout)
+        }
+    }
+
+    #[cfg_attr(feature = "aggressive-inline", inline(always))]
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        if in_sensitive_scope() {
+            self.secure.alloc_zeroed(layout)
+        } else {
+            self.alloc.alloc_zeroed(layout)
+        }
+    }
+
+    #[cfg_attr(feature = "aggressive-inline", inline(always))]
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        if self.secure.owns(ptr) {
+            self.secure.realloc(ptr, layout, new_size)
+        } else {
+            self.alloc.realloc(ptr, layout, new_size)
+        }
+    }
+}
@@ -0,0 +1,181 @@
+use core::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+/// Counters describing what the wrappers it is attached to did, updated with relaxed atomics.
+///
+/// Read them with [`snapshot`](Self::snapshot). Each counter is exact on its own, but a
+/// snapshot taken while other threads allocate may mix counters from slightly different
+/// moments.
+#[derive(Debug, Default)]
+pub struct Stats {
+    allocations: AtomicU64,
+    deallocations: AtomicU64,
+    bytes_wiped: AtomicU64,
+    live_bytes: AtomicUsize,
+    peak_live_bytes: AtomicUsize,
+    wipe_nanos: AtomicU64,
+    dealloc_sizes: AtomicHistogram,
+    wipe_sizes: AtomicHistogram,
+    wipe_latency: AtomicHistogram,
+}
+
+/// The counters of a [`Stats`] at one point in time.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StatsSnapshot {
+    /// Blocks handed out, including blocks moved by a reallocation.
+    pub allocations: u64,
+    /// Blocks released, including blocks moved away from by a reallocation.
+    pub deallocations: u64,
+    /// Bytes overwritten by the wipe strategy.
+    pub bytes_wiped: u64,
+    /// Bytes currently allocated, as requested by the layouts.
+    pub live_bytes: usize,
+    /// Highest value `live_bytes` has reached.
+    pub peak_live_bytes: usize,
+    /// Time spent in the wipe strategy, in nanoseconds. Only measured with the `std` feature.
+    pub wipe_nanos: u64,
+    /// Number of deallocations by the size of the block.
+    pub dealloc_sizes: Histogram,
+    /// Bytes wiped by the length of the wipe, which covers the whole block on deallocation
+    /// and the discarded tail when shrinking.
+    pub wipe_sizes: Histogram,
+    /// Number of wipes by their duration in nanoseconds. Only measured with the `std` feature.
+    pub wipe_latency: Histogram,
+}
+
+/// Values grouped into power-of-two size classes.
+///
+/// Bucket 0 holds size 0 and bucket `i` sizes from `2^(i - 1)` to `2^i - 1`, i.e. sizes whose
+/// highest set bit is bit `i - 1`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Histogram {
+    /// The value of each bucket.
+    pub buckets: [u64; Histogram::BUCKETS],
+}
+
+impl Histogram {
+    /// Number of buckets, enough for every `usize`.
+    pub const BUCKETS: usize = usize::BITS as usize + 1;
+
+    /// The bucket `size` falls into.
+    pub const fn bucket(size: usize) -> usize {
+        (usize::BITS - size.leading_zeros()) as usize
+    }
+
+    /// The largest size falling into `bucket`.
+    pub const fn upper_bound(bucket: usize) -> usize {
+        match bucket {
+            0 => 0,
+            _ => usize::MAX >> (usize::BITS as usize - bucket),
+        }
+    }
+
+    /// Sum of all buckets.
+    pub fn total(&self) -> u64 {
+        self.buckets.iter().sum()
+    }
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self {
+            buckets: [0; Self::BUCKETS],
+        }
+    }
+}
+
+#[derive(Debug)]
+struct AtomicHistogram([AtomicU64; Histogram::BUCKETS]);
+
+impl AtomicHistogram {
+    const fn new() -> Self {
+        Self([const { AtomicU64::new(0) }; Histogram::BUCKETS])
+    }
+
+    fn add(&self, size: usize, value: u64) {
+        self.0[Histogram::bucket(size)].fetch_add(value, Ordering::Relaxed);
+    }
+
+    fn load(&self) -> Histogram {
+        Histogram {
+            buckets: core::array::from_fn(|bucket| self.0[bucket].load(Ordering::Relaxed)),
+        }
+    }
+}
+
+impl Default for AtomicHistogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Stats {
+    /// All counters at zero.
+    pub const fn new() -> Self {
+        Self {
+            allocations: AtomicU64::new(0),
+            deallocations: AtomicU64::new(0),
+            bytes_wiped: AtomicU64::new(0),
+            live_bytes: AtomicUsize::new(0),
+            peak_live_bytes: AtomicUsize::new(0),
+            wipe_nanos: AtomicU64::new(0),
+            dealloc_sizes: AtomicHistogram::new(),
+            wipe_sizes: AtomicHistogram::new(),
+            wipe_latency: AtomicHistogram::new(),
+        }
+    }
+
+    /// Reads all counters.
+    pub fn snapshot(&self) -> StatsSnapshot {
+        StatsSnapshot {
+            allocations: self.allocations.load(Ordering::Relaxed),
+            deallocations: self.deallocations.load(Ordering::Relaxed),
+            bytes_wiped: self.bytes_wiped.load(Ordering::Relaxed),
+            live_bytes: self.live_bytes.load(Ordering::Relaxed),
+            peak_live_bytes: self.peak_live_bytes.load(Ordering::Relaxed),
+            wipe_nanos: self.wipe_nanos.load(Ordering::Relaxed),
+            dealloc_sizes: self.dealloc_sizes.load(),
+            wipe_sizes: self.wipe_sizes.load(),
+            wipe_latency: self.wipe_latency.load(),
+        }
+    }
+
+    fn add_live(&self, size: usize) {
+        let live = self.live_bytes.fetch_add(size, Ordering::Relaxed).wrapping_add(size);
+        self.peak_live_bytes.fetch_max(live, Ordering::Relaxed);
+    }
+
+    pub(crate) fn allocated(&self, size: usize) {
+        self.allocations.fetch_add(1, Ordering::Relaxed);
+        self.add_live(size);
+    }
+
+    pub(crate) fn deallocated(&self, size: usize) {
+        self.deallocations.fetch_add(1, Ordering::Relaxed);
+        self.dealloc_sizes.add(size, 1);
+        self.live_bytes.fetch_sub(size, Ordering::Relaxed);
+    }
+
+    /// Records a block resized without moving it.
+    pub(crate) fn resized(&self, old_size: usize, new_size: usize) {
+        if new_size > old_size {
+            self.add_live(new_size - old_size);
+        } else {
+            self.live_bytes.fetch_sub(old_size - new_size, Ordering::Relaxed);
+        }
+    }
+
+    /// Runs `wipe` over `len` bytes, timing it with the `std` feature.
+    pub(crate) fn wiping(&self, len: usize, wipe: impl FnOnce()) {
+        #[cfg(feature = "std")]
+        let start = std::time::Instant::now();
+        wipe();
+        #[cfg(feature = "std")]
+        {
+            let nanos = start.elapsed().as_nanos() as u64;
+            self.wipe_nanos.fetch_add(nanos, Ordering::Relaxed);
+            self.wipe_latency.add(nanos as usize, 1);
+        }
+        self.bytes_wiped.fetch_add(len as u64, Ordering::Relaxed);
+        self.wipe_sizes.add(len, len as u64);
+    }
+}
@@ -0,0 +1,39 @@
+/// Emits a `tracing` event with the `tracing` feature, and does nothing (without evaluating
+/// its arguments) otherwise.
+///
+/// Events are dropped while the current thread is already emitting one, so a subscriber that
+/// allocates cannot recurse back into the allocator's tracing.
+#[cfg(feature = "tracing")]
+macro_rules! event {
+    ($($arg:tt)*) => {
+        $crate::trace::reentrancy_guarded(|| tracing::event!($($arg)*))
+    };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! event {
+    ($($arg:tt)*) => {};
+}
+
+pub(crate) use event;
+
+/// Wipes at least this long are reported as large.
+#[cfg(feature = "tracing")]
+pub(crate) const LARGE_WIPE: usize = 4096;
+
+#[cfg(feature = "tracing")]
+pub(crate) fn reentrancy_guarded(emit: impl FnOnce()) {
+    use core::cell::Cell;
+
+    std::thread_local! {
+        static EMITTING: Cell<bool> = const { Cell::new(false) };
+    }
+
+    // The thread-local is gone while the thread is being torn down; drop those events too.
+    let _ = EMITTING.try_with(|emitting| {
+        if !emitting.replace(true) {
+            emit();
+            emitting.set(false);
+        }
+    });
+}
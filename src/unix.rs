@@ -0,0 +1,20 @@
+pub(crate) fn page_size() -> usize {
+    unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize }
+}
+
+pub(crate) fn errno() -> i32 {
+    std::io::Error::last_os_error().raw_os_error().unwrap_or(0)
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) unsafe fn exclude_from_dumps(ptr: *mut libc::c_void, len: usize) {
+    libc::madvise(ptr, len, libc::MADV_DONTDUMP);
+}
+
+#[cfg(target_os = "freebsd")]
+pub(crate) unsafe fn exclude_from_dumps(ptr: *mut libc::c_void, len: usize) {
+    libc::madvise(ptr, len, libc::MADV_NOCORE);
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "freebsd")))]
+pub(crate) unsafe fn exclude_from_dumps(_ptr: *mut libc::c_void, _len: usize) {}
@@ -0,0 +1,207 @@
+use core::sync::atomic::{compiler_fence, AtomicU64, Ordering};
+
+/// The routine used to overwrite memory before it is handed back to the inner allocator.
+///
+/// Implementations must make sure the writes cannot be elided by the optimizer, e.g. by using
+/// volatile stores followed by a compiler fence.
+pub trait WipeStrategy {
+    /// Overwrites `len` bytes starting at `ptr`.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be valid for writes of `len` bytes.
+    unsafe fn wipe(&self, ptr: *mut u8, len: usize);
+}
+
+/// Zeroes memory one byte at a time with `write_volatile`.
+///
+/// This is the default strategy of both wrappers.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct VolatileBytes;
+
+impl WipeStrategy for VolatileBytes {
+    #[cfg_attr(feature = "aggressive-inline", inline)]
+    unsafe fn wipe(&self, ptr: *mut u8, len: usize) {
+        for i in 0..len {
+            core::ptr::write_volatile(ptr.add(i), 0);
+        }
+        compiler_fence(Ordering::SeqCst);
+    }
+}
+
+/// One overwrite of a [`MultiPass`] wipe.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pass {
+    /// Writes the given byte everywhere.
+    Byte(u8),
+    /// Writes pseudo-random bytes. They are not suitable for cryptographic use, only for
+    /// making the previous contents unrecognizable.
+    Random,
+}
+
+/// Overwrites memory once per [`Pass`], in order, e.g. `0xff`, random, `0x00` with
+/// `MultiPass::new(&[Pass::Byte(0xff), Pass::Random, Pass::Byte(0)])`.
+///
+/// Every pass uses volatile stores and is followed by a compiler fence, so no pass can be
+/// optimized away because a later one overwrites it.
+#[derive(Clone, Copy, Debug)]
+pub struct MultiPass<'a> {
+    passes: &'a [Pass],
+}
+
+impl<'a> MultiPass<'a> {
+    /// Performs `passes` in order on every wipe.
+    ///
+    /// # Panics
+    ///
+    /// If `passes` is empty, since such a strategy would leave freed memory untouched. In a
+    /// `static` this is a compile error.
+    pub const fn new(passes: &'a [Pass]) -> Self {
+        assert!(!passes.is_empty(), "MultiPass needs at least one pass");
+        Self { passes }
+    }
+
+    /// The passes performed, in order.
+    pub const fn passes(&self) -> &'a [Pass] {
+        self.passes
+    }
+}
+
+impl WipeStrategy for MultiPass<'_> {
+    unsafe fn wipe(&self, ptr: *mut u8, len: usize) {
+        for pass in self.passes {
+            match *pass {
+                Pass::Byte(byte) => {
+                    for i in 0..len {
+                        core::ptr::write_volatile(ptr.add(i), byte);
+                    }
+                }
+                Pass::Random => {
+                    let mut rng = XorShift::seeded(ptr as u64);
+                    for chunk in (0..len).step_by(8) {
+                        let bytes = rng.next().to_ne_bytes();
+                        for (i, &byte) in bytes.iter().enumerate().take(len - chunk) {
+                            core::ptr::write_volatile(ptr.add(chunk + i), byte);
+                        }
+                    }
+                }
+            }
+            compiler_fence(Ordering::SeqCst);
+        }
+    }
+}
+
+/// xorshift64*, seeded from a global counter so consecutive wipes of the same address differ.
+struct XorShift(u64);
+
+impl XorShift {
+    fn seeded(salt: u64) -> Self {
+        static COUNTER: AtomicU64 = AtomicU64::new(0x9e37_79b9_7f4a_7c15);
+        let mut seed = COUNTER.fetch_add(0x9e37_79b9_7f4a_7c15, Ordering::Relaxed) ^ salt;
+        // splitmix64 finalizer, which also keeps the state non-zero in practice.
+        seed = (seed ^ (seed >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        seed = (seed ^ (seed >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        Self((seed ^ (seed >> 31)) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+}
+
+/// Zeroes memory with the widest volatile stores available.
+///
+/// Bytes before the first aligned word and after the last one are cleared individually, the
+/// aligned body with 32-byte AVX2 or 16-byte SSE2 stores on x86_64 (picked once at runtime by
+/// CPU feature detection) and with `usize` stores elsewhere.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WideVolatile;
+
+impl WipeStrategy for WideVolatile {
+    #[cfg_attr(feature = "aggressive-inline", inline)]
+    unsafe fn wipe(&self, ptr: *mut u8, len: usize) {
+        #[cfg(target_arch = "x86_64")]
+        if x86::has_avx2() {
+            x86::zero_avx2(ptr, len);
+        } else {
+            x86::zero_sse2(ptr, len);
+        }
+        #[cfg(not(target_arch = "x86_64"))]
+        zero_words(ptr, len, 0usize);
+        compiler_fence(Ordering::SeqCst);
+    }
+}
+
+/// Zeroes `len` bytes at `ptr`, storing `zero` (an all-zero `T`) over the `T`-aligned body.
+#[inline(always)]
+unsafe fn zero_words<T: Copy>(ptr: *mut u8, len: usize, zero: T) {
+    let head = ptr.align_offset(core::mem::align_of::<T>()).min(len);
+    let words = (len - head) / core::mem::size_of::<T>();
+    let tail = head + words * core::mem::size_of::<T>();
+    for i in 0..head {
+        core::ptr::write_volatile(ptr.add(i), 0);
+    }
+    let body = ptr.add(head) as *mut T;
+    for i in 0..words {
+        core::ptr::write_volatile(body.add(i), zero);
+    }
+    for i in tail..len {
+        core::ptr::write_volatile(ptr.add(i), 0);
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use core::arch::x86_64::*;
+    use core::sync::atomic::{AtomicU8, Ordering};
+
+    const UNKNOWN: u8 = 0;
+    const ABSENT: u8 = 1;
+    const PRESENT: u8 = 2;
+
+    static AVX2: AtomicU8 = AtomicU8::new(UNKNOWN);
+
+    pub fn has_avx2() -> bool {
+        if cfg!(target_feature = "avx2") {
+            return true;
+        }
+        match AVX2.load(Ordering::Relaxed) {
+            PRESENT => true,
+            ABSENT => false,
+            _ => {
+                let found = detect_avx2();
+                AVX2.store(if found { PRESENT } else { ABSENT }, Ordering::Relaxed);
+                found
+            }
+        }
+    }
+
+    fn detect_avx2() -> bool {
+        let leaf1 = __cpuid(1);
+        let osxsave = leaf1.ecx & (1 << 27) != 0;
+        let avx = leaf1.ecx & (1 << 28) != 0;
+        if !(osxsave && avx) || __cpuid(0).eax < 7 {
+            return false;
+        }
+        // The OS must save the upper halves of the ymm registers on context switches.
+        let xcr0 = unsafe { xgetbv() };
+        xcr0 & 0b110 == 0b110 && __cpuid_count(7, 0).ebx & (1 << 5) != 0
+    }
+
+    #[target_feature(enable = "xsave")]
+    unsafe fn xgetbv() -> u64 {
+        _xgetbv(0)
+    }
+
+    pub unsafe fn zero_sse2(ptr: *mut u8, len: usize) {
+        super::zero_words(ptr, len, _mm_setzero_si128());
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn zero_avx2(ptr: *mut u8, len: usize) {
+        super::zero_words(ptr, len, _mm256_setzero_si256());
+    }
+}
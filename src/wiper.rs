@@ -0,0 +1,162 @@
+use core::alloc::Layout;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::trace::event;
+use crate::double_free::FreedBlocks;
+use crate::{DeallocHook, Registry, Stats, WipePolicy, WipeStrategy, ENABLED};
+
+/// The wipe strategy and settings shared by both wrappers, and the bookkeeping they do around
+/// every allocation and deallocation.
+pub(crate) struct Wiper<W> {
+    pub(crate) wipe: W,
+    pub(crate) usable_size: bool,
+    pub(crate) junk: Option<u8>,
+    pub(crate) enabled: AtomicBool,
+    pub(crate) policy: Option<&'static dyn WipePolicy>,
+    pub(crate) double_free: Option<&'static dyn FreedBlocks>,
+    pub(crate) registry: Option<&'static Registry>,
+    pub(crate) stats: Option<&'static Stats>,
+    pub(crate) hook: Option<&'static dyn DeallocHook>,
+}
+
+impl<W: WipeStrategy> Wiper<W> {
+    pub(crate) const fn new(wipe: W) -> Self {
+        Self {
+            wipe,
+            usable_size: false,
+            junk: None,
+            enabled: AtomicBool::new(true),
+            policy: None,
+            double_free: None,
+            registry: None,
+            stats: None,
+            hook: None,
+        }
+    }
+
+    #[cfg_attr(feature = "aggressive-inline", inline(always))]
+    pub(crate) fn wiping(&self, layout: Layout) -> bool {
+        // Relaxed loads still observe any store that happens-before them.
+        ENABLED.load(Ordering::Relaxed)
+            && self.enabled.load(Ordering::Relaxed)
+            && self.policy.is_none_or(|policy| {
+                let wipe = policy.should_wipe(layout);
+                event!(
+                    tracing::Level::TRACE,
+                    size = layout.size(),
+                    align = layout.align(),
+                    wipe,
+                    "wipe policy decision"
+                );
+                wipe
+            })
+    }
+
+    /// Tells the double-free tracker, registry and stats, if any, that `ptr` was handed out
+    /// for `size` bytes, of which the caller may use `len`.
+    #[cfg_attr(feature = "aggressive-inline", inline(always))]
+    pub(crate) fn allocated(&self, ptr: *mut u8, size: usize, len: usize) {
+        if ptr.is_null() {
+            return;
+        }
+        if let Some(tracker) = self.double_free {
+            tracker.allocated(ptr);
+        }
+        if let Some(registry) = self.registry {
+            registry.insert(ptr, len);
+        }
+        if let Some(stats) = self.stats {
+            stats.allocated(size);
+        }
+    }
+
+    /// Tells the registry and stats, if any, that `ptr` was resized in place.
+    #[cfg_attr(feature = "aggressive-inline", inline(always))]
+    pub(crate) fn resized(&self, ptr: *mut u8, old_size: usize, new_size: usize) {
+        if let Some(registry) = self.registry {
+            registry.resize(ptr, new_size);
+        }
+        if let Some(stats) = self.stats {
+            stats.resized(old_size, new_size);
+        }
+    }
+
+    /// Prepares the block at `ptr` to be handed back to the inner allocator: checks it is not
+    /// a double free, updates the registry and stats, and wipes `wipe_len()` bytes between the
+    /// hook calls. Returns `false` if the block must not be freed.
+    #[cfg_attr(feature = "aggressive-inline", inline(always))]
+    pub(crate) unsafe fn release(
+        &self,
+        ptr: *mut u8,
+        layout: Layout,
+        wipe_len: impl FnOnce() -> usize,
+    ) -> bool {
+        if !self.double_free.is_none_or(|tracker| tracker.freeing(ptr, layout)) {
+            return false;
+        }
+        if let Some(registry) = self.registry {
+            registry.remove(ptr);
+        }
+        if let Some(stats) = self.stats {
+            stats.deallocated(layout.size());
+        }
+        if let Some(hook) = self.hook {
+            hook.before_wipe(ptr, layout);
+        }
+        if self.wiping(layout) {
+            self.wipe_bytes(ptr, wipe_len());
+        }
+        if let Some(hook) = self.hook {
+            hook.after_wipe(ptr, layout);
+        }
+        true
+    }
+
+    /// Wipes the tail an in-place shrink of `ptr` from `layout` to `new_size` bytes cut off,
+    /// as far as the block still reaches `usable` bytes, between the hook calls; the backend
+    /// wipes the rest when it takes it back.
+    #[cfg_attr(feature = "aggressive-inline", inline(always))]
+    pub(crate) unsafe fn wipe_tail(
+        &self,
+        ptr: *mut u8,
+        layout: Layout,
+        new_size: usize,
+        usable: usize,
+    ) {
+        let end = layout.size().min(usable);
+        if new_size >= end {
+            return;
+        }
+        let tail = ptr.add(new_size);
+        let tail_layout = Layout::from_size_align_unchecked(end - new_size, 1);
+        if let Some(hook) = self.hook {
+            hook.before_wipe(tail, tail_layout);
+        }
+        if self.wiping(layout) {
+            self.wipe_bytes(tail, tail_layout.size());
+        }
+        if let Some(hook) = self.hook {
+            hook.after_wipe(tail, tail_layout);
+        }
+    }
+
+    /// Runs the wipe strategy, accounting for it in the stats, if any.
+    #[cfg_attr(feature = "aggressive-inline", inline(always))]
+    pub(crate) unsafe fn wipe_bytes(&self, ptr: *mut u8, len: usize) {
+        #[cfg(feature = "tracing")]
+        if len >= crate::trace::LARGE_WIPE {
+            event!(tracing::Level::DEBUG, ptr = ?ptr, len, "large wipe");
+        }
+        match self.stats {
+            Some(stats) => stats.wiping(len, || self.wipe.wipe(ptr, len)),
+            None => self.wipe.wipe(ptr, len),
+        }
+    }
+
+    #[cfg_attr(feature = "aggressive-inline", inline(always))]
+    pub(crate) unsafe fn fill_junk(&self, ptr: *mut u8, len: usize) {
+        if let Some(pattern) = self.junk {
+            ptr.write_bytes(pattern, len);
+        }
+    }
+}